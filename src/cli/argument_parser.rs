@@ -46,7 +46,10 @@ pub fn parse_args(args: Vec<&'_ str>) -> Result<Arguments<'_>> {
             } else {
                 Vec::new()
             };
-        } else if arg.starts_with('-') {
+        } else if arg.starts_with('-')
+            // Something like -1w is a date going back in time, not the flags 1 and w
+            && !arg[1..].starts_with(|char: char| char.is_ascii_digit())
+        {
             let flag_letters = arg.strip_prefix('-').unwrap();
 
             for letter in flag_letters.graphemes(true) {
@@ -95,11 +98,31 @@ pub fn parse_args(args: Vec<&'_ str>) -> Result<Arguments<'_>> {
     })
 }
 
+/// Removes an argument which applies to the whole program, like `--config <FILE>`, from the raw
+/// arguments and returns its value. This happens before anything else is parsed, as these are needed
+/// before it's known whether a command is being run at all
+pub fn take_global_argument(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let flag = format!("--{}", name);
+    let index = match args.iter().skip(1).position(|arg| *arg == flag) {
+        Some(index) => index + 1,
+        None => return Ok(None),
+    };
+
+    match args.get(index + 1) {
+        Some(value) if !value.starts_with('-') => {
+            let value = value.to_owned();
+            args.drain(index..index + 2);
+            Ok(Some(value))
+        }
+        _ => Err(format!("{} needs a value", flag)),
+    }
+}
+
 pub fn parse_ids(ids: Option<&Vec<&str>>) -> Result<Vec<i64>, String> {
     if let Some(task_ids) = ids {
         let mut parsed_task_ids: Vec<i64> = vec![];
         for id in task_ids {
-            let last_id_bit = id.split('.').last().unwrap_or_default();
+            let last_id_bit = id.split('.').next_back().unwrap_or_default();
             if let Ok(id) = last_id_bit.parse::<i64>() {
                 parsed_task_ids.push(id);
             } else {
@@ -0,0 +1,930 @@
+use super::argument_parser::Arguments;
+
+/// An argument which a subcommand accepts, like `--id <ID>` or `--raw`
+pub struct Argument {
+    pub name: &'static str,
+    /// A single letter which can be used instead of the name, like `-i` for `--id`
+    pub short: Option<&'static str>,
+    /// What the argument's value is called in usage text, or None if the argument is a flag which
+    /// doesn't take a value
+    pub value: Option<&'static str>,
+    pub required: bool,
+    pub description: &'static str,
+}
+
+/// A subcommand of the CLI. This is the only place subcommands and their arguments are described,
+/// so the help text is generated from it and the arguments passed to a subcommand are checked
+/// against it before the subcommand runs
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// What the subcommand expects after its name, other than arguments, in usage text
+    pub operands: Option<&'static str>,
+    pub summary: &'static str,
+    pub arguments: &'static [Argument],
+    pub examples: &'static [&'static str],
+}
+
+/// Arguments which every subcommand accepts
+pub const GLOBAL_ARGUMENTS: &[Argument] = &[
+    Argument {
+        name: "config",
+        short: None,
+        value: Some("FILE"),
+        required: false,
+        description: "Read settings from this config file instead of the usual one",
+    },
+    Argument {
+        name: "db",
+        short: None,
+        value: Some("FILE"),
+        required: false,
+        description: "Use the tasks in this database file instead of a list",
+    },
+    Argument {
+        name: "list",
+        short: None,
+        value: Some("NAME"),
+        required: false,
+        description: "Use one of your other task lists, see the lists command",
+    },
+    Argument {
+        name: "help",
+        short: Some("h"),
+        value: None,
+        required: false,
+        description: "Show help for the command instead of running it",
+    },
+];
+
+const RAW: Argument = Argument {
+    name: "raw",
+    short: None,
+    value: None,
+    required: false,
+    description: "Print the tasks without colors or borders, for use in scripts",
+};
+
+const FORMAT: Argument = Argument {
+    name: "format",
+    short: None,
+    value: Some("FORMAT"),
+    required: false,
+    description: "How to print the tasks: table, raw, json or jsonl",
+};
+
+const ID: Argument = Argument {
+    name: "id",
+    short: Some("i"),
+    value: Some("ID"),
+    required: true,
+    description: "The task to change. Subtasks can be written as 1.2, or just 2",
+};
+
+const IDS: Argument = Argument {
+    name: "id",
+    short: Some("i"),
+    value: Some("ID..."),
+    required: true,
+    description: "The tasks to change. Subtasks can be written as 1.2, or just 2",
+};
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "list",
+        aliases: &["ls"],
+        operands: None,
+        summary: "List your tasks along with their subtasks",
+        arguments: &[
+            Argument {
+                name: "search",
+                short: None,
+                value: Some("TEXT"),
+                required: false,
+                description: "Only list tasks whose description or notes match the text",
+            },
+            Argument {
+                name: "sort",
+                short: None,
+                value: Some("ORDER"),
+                required: false,
+                description: "Sort the tasks by id or priority, instead of the order in the config",
+            },
+            Argument {
+                name: "tag",
+                short: None,
+                value: Some("TAG..."),
+                required: false,
+                description: "Only list tasks with all of the tags or @contexts given",
+            },
+            Argument {
+                name: "created-since",
+                short: None,
+                value: Some("DATE"),
+                required: false,
+                description: "Only list tasks created on or after the date",
+            },
+            Argument {
+                name: "completed-since",
+                short: None,
+                value: Some("DATE"),
+                required: false,
+                description: "Only list tasks marked as done on or after the date",
+            },
+            Argument {
+                name: "blocked",
+                short: None,
+                value: None,
+                required: false,
+                description: "Only list tasks which are waiting on other tasks to be done",
+            },
+            Argument {
+                name: "ready",
+                short: None,
+                value: None,
+                required: false,
+                description: "Only list tasks which aren't done and aren't waiting on other tasks",
+            },
+            Argument {
+                name: "all",
+                short: Some("a"),
+                value: None,
+                required: false,
+                description: "List tasks which are done even if the config hides them",
+            },
+            RAW,
+            FORMAT,
+        ],
+        examples: &[
+            "list --sort priority",
+            "list --tag @work +urgent",
+            "list --completed-since -1w",
+            "list --ready --sort priority",
+            "list --format jsonl",
+        ],
+    },
+    Command {
+        name: "add",
+        aliases: &["create"],
+        operands: None,
+        summary: "Add a new task",
+        arguments: &[
+            Argument {
+                name: "name",
+                short: None,
+                value: Some("NAME..."),
+                required: true,
+                description: "What the task is. Any +tags or @contexts in it are added to the task",
+            },
+            Argument {
+                name: "parent",
+                short: None,
+                value: Some("ID"),
+                required: false,
+                description: "Add the task as a subtask of another task",
+            },
+            Argument {
+                name: "due",
+                short: None,
+                value: Some("DATE"),
+                required: false,
+                description: "When the task is due, like tomorrow, +3d, friday or 2023-01-31 17:30",
+            },
+            Argument {
+                name: "scheduled",
+                short: None,
+                value: Some("DATE"),
+                required: false,
+                description: "When you plan to start the task, in the same format as --due",
+            },
+            Argument {
+                name: "priority",
+                short: None,
+                value: Some("PRIORITY"),
+                required: false,
+                description: "How important the task is: none, low, medium, high or 0 to 3",
+            },
+            Argument {
+                name: "notes",
+                short: None,
+                value: Some("NOTES..."),
+                required: false,
+                description: "Longer notes to keep with the task",
+            },
+            Argument {
+                name: "repeat",
+                short: None,
+                value: Some("RULE..."),
+                required: false,
+                description:
+                    "Make a new copy each time the task is done, like weekly or every mon,thu",
+            },
+            Argument {
+                name: "complete-with-subtasks",
+                short: None,
+                value: Some("SETTING"),
+                required: false,
+                description: "Whether the task is done once all of its subtasks are: yes or no",
+            },
+            Argument {
+                name: "estimate",
+                short: None,
+                value: Some("LENGTH"),
+                required: false,
+                description:
+                    "How long the task should take, not counting its subtasks, like 2h or 30m",
+            },
+            RAW,
+            FORMAT,
+        ],
+        examples: &[
+            "add --name Buy milk +errands @shops --due tomorrow",
+            "add --name Write the report --parent 3 --priority high",
+            "add --name Send invoices --due 2023-01-31 --repeat monthly",
+            "add --name Draft the slides --parent 4 --estimate 1h30m",
+        ],
+    },
+    Command {
+        name: "remove",
+        aliases: &["del"],
+        operands: None,
+        summary: "Move tasks along with all of their subtasks to the trash",
+        arguments: &[IDS, RAW, FORMAT],
+        examples: &["remove --id 3", "del -i 1.2 4"],
+    },
+    Command {
+        name: "trash",
+        aliases: &[],
+        operands: None,
+        summary: "List the tasks in the trash",
+        arguments: &[RAW, FORMAT],
+        examples: &["trash", "trash --format json"],
+    },
+    Command {
+        name: "restore",
+        aliases: &[],
+        operands: None,
+        summary: "Bring tasks back out of the trash, along with the subtasks removed with them",
+        arguments: &[
+            Argument {
+                description: "The tasks to bring back",
+                ..IDS
+            },
+            RAW,
+            FORMAT,
+        ],
+        examples: &["restore --id 3", "restore -i 3 7"],
+    },
+    Command {
+        name: "purge",
+        aliases: &[],
+        operands: None,
+        summary: "Delete the tasks in the trash for good",
+        arguments: &[Argument {
+            name: "older-than",
+            short: None,
+            value: Some("DAYS"),
+            required: false,
+            description: "Only delete tasks which were removed more than this many days ago",
+        }],
+        examples: &["purge", "purge --older-than 30"],
+    },
+    Command {
+        name: "done",
+        aliases: &[],
+        operands: None,
+        summary: "Mark tasks as done. Tasks which repeat get a new copy for next time",
+        arguments: &[
+            IDS,
+            Argument {
+                name: "recursive",
+                short: Some("r"),
+                value: None,
+                required: false,
+                description: "Mark all of the tasks' subtasks as done too",
+            },
+            Argument {
+                name: "force",
+                short: Some("f"),
+                value: None,
+                required: false,
+                description: "Mark the tasks as done even if the tasks blocking them aren't",
+            },
+            RAW,
+            FORMAT,
+        ],
+        examples: &["done --id 3 4", "done -r -i 1", "done --force --id 5"],
+    },
+    Command {
+        name: "undone",
+        aliases: &[],
+        operands: None,
+        summary: "Mark tasks as not done",
+        arguments: &[
+            IDS,
+            Argument {
+                name: "recursive",
+                short: Some("r"),
+                value: None,
+                required: false,
+                description: "Mark all of the tasks' subtasks as not done too",
+            },
+            RAW,
+            FORMAT,
+        ],
+        examples: &["undone --id 3", "undone -r -i 1"],
+    },
+    Command {
+        name: "edit",
+        aliases: &["update"],
+        operands: None,
+        summary: "Change a task. Anything not passed is left as it is",
+        arguments: &[
+            ID,
+            Argument {
+                name: "name",
+                short: None,
+                value: Some("NAME..."),
+                required: false,
+                description: "The new description. Any +tags or @contexts in it are added too",
+            },
+            Argument {
+                name: "due",
+                short: None,
+                value: Some("DATE"),
+                required: false,
+                description: "When the task is due, or none to remove the due date",
+            },
+            Argument {
+                name: "scheduled",
+                short: None,
+                value: Some("DATE"),
+                required: false,
+                description: "When you plan to start the task, or none to remove the date",
+            },
+            Argument {
+                name: "priority",
+                short: None,
+                value: Some("PRIORITY"),
+                required: false,
+                description: "How important the task is: none, low, medium, high or 0 to 3",
+            },
+            Argument {
+                name: "notes",
+                short: None,
+                value: Some("NOTES..."),
+                required: false,
+                description: "Replace the task's notes",
+            },
+            Argument {
+                name: "repeat",
+                short: None,
+                value: Some("RULE..."),
+                required: false,
+                description: "How often the task repeats, or none to stop it repeating",
+            },
+            Argument {
+                name: "complete-with-subtasks",
+                short: None,
+                value: Some("SETTING"),
+                required: false,
+                description:
+                    "Whether the task is done once all of its subtasks are: yes, no or default",
+            },
+            Argument {
+                name: "estimate",
+                short: None,
+                value: Some("LENGTH"),
+                required: false,
+                description: "How long the task should take, or none to remove the estimate",
+            },
+            RAW,
+            FORMAT,
+        ],
+        examples: &[
+            "edit --id 3 --name Buy oat milk",
+            "edit --id 3 --due none --priority low",
+            "edit --id 3 --repeat every 2 weeks after completion",
+            "edit --id 3 --complete-with-subtasks yes",
+            "edit --id 3 --estimate 45m",
+        ],
+    },
+    Command {
+        name: "notes",
+        aliases: &["note"],
+        operands: None,
+        summary: "Show or change a task's notes",
+        arguments: &[
+            ID,
+            Argument {
+                name: "set",
+                short: None,
+                value: Some("NOTES..."),
+                required: false,
+                description: "Replace the notes",
+            },
+            Argument {
+                name: "clear",
+                short: None,
+                value: None,
+                required: false,
+                description: "Remove the notes",
+            },
+            Argument {
+                name: "edit",
+                short: Some("e"),
+                value: None,
+                required: false,
+                description: "Edit the notes in $VISUAL or $EDITOR",
+            },
+            FORMAT,
+        ],
+        examples: &["notes --id 3", "notes --id 3 --edit"],
+    },
+    Command {
+        name: "priority",
+        aliases: &["prioritize", "prioritise"],
+        operands: None,
+        summary: "Change how important tasks are",
+        arguments: &[
+            IDS,
+            Argument {
+                name: "priority",
+                short: Some("p"),
+                value: Some("PRIORITY"),
+                required: true,
+                description: "none, low, medium, high or 0 to 3",
+            },
+            RAW,
+            FORMAT,
+        ],
+        examples: &["priority --id 3 4 -p high"],
+    },
+    Command {
+        name: "tag",
+        aliases: &[],
+        operands: None,
+        summary: "Add tags or @contexts to tasks",
+        arguments: &[
+            IDS,
+            Argument {
+                name: "tag",
+                short: Some("t"),
+                value: Some("TAG..."),
+                required: true,
+                description: "The tags to add. Tags without a + or @ are taken to be +tags",
+            },
+            FORMAT,
+        ],
+        examples: &["tag --id 3 -t @home errands"],
+    },
+    Command {
+        name: "untag",
+        aliases: &[],
+        operands: None,
+        summary: "Remove tags or @contexts from tasks",
+        arguments: &[
+            IDS,
+            Argument {
+                name: "tag",
+                short: Some("t"),
+                value: Some("TAG..."),
+                required: true,
+                description: "The tags to remove",
+            },
+            FORMAT,
+        ],
+        examples: &["untag --id 3 -t @home"],
+    },
+    Command {
+        name: "block",
+        aliases: &[],
+        operands: None,
+        summary: "Mark tasks as blocked until other tasks are done",
+        arguments: &[
+            IDS,
+            Argument {
+                name: "by",
+                short: Some("b"),
+                value: Some("ID..."),
+                required: true,
+                description: "The tasks which have to be done first",
+            },
+            FORMAT,
+        ],
+        examples: &["block --id 4 --by 2 3", "block -i 1.2 -b 5"],
+    },
+    Command {
+        name: "unblock",
+        aliases: &[],
+        operands: None,
+        summary: "Stop tasks from being blocked by other tasks",
+        arguments: &[
+            IDS,
+            Argument {
+                name: "by",
+                short: Some("b"),
+                value: Some("ID..."),
+                required: true,
+                description: "The tasks which shouldn't block them any more",
+            },
+            FORMAT,
+        ],
+        examples: &["unblock --id 4 --by 2"],
+    },
+    Command {
+        name: "move",
+        aliases: &["mv"],
+        operands: None,
+        summary: "Move a task, along with its subtasks, under a different parent",
+        arguments: &[
+            ID,
+            Argument {
+                name: "parent",
+                short: None,
+                value: Some("ID"),
+                required: false,
+                description: "The task to move it under",
+            },
+            Argument {
+                name: "top",
+                short: None,
+                value: None,
+                required: false,
+                description: "Make it a top-level task instead",
+            },
+            RAW,
+            FORMAT,
+        ],
+        examples: &["move --id 4 --parent 1", "mv --id 1.4 --top"],
+    },
+    Command {
+        name: "start",
+        aliases: &[],
+        operands: None,
+        summary: "Start timing a task, stopping whichever task was being timed before",
+        arguments: &[Argument {
+            description: "The task to time. Subtasks can be written as 1.2, or just 2",
+            ..ID
+        }],
+        examples: &["start --id 3", "start -i 1.2"],
+    },
+    Command {
+        name: "stop",
+        aliases: &[],
+        operands: None,
+        summary: "Stop timing the task being timed",
+        arguments: &[],
+        examples: &["stop"],
+    },
+    Command {
+        name: "log",
+        aliases: &[],
+        operands: None,
+        summary: "Record time spent on a task without timing it",
+        arguments: &[
+            Argument {
+                description:
+                    "The task the time was spent on. Subtasks can be written as 1.2, or just 2",
+                ..ID
+            },
+            Argument {
+                name: "time",
+                short: Some("t"),
+                value: Some("LENGTH"),
+                required: true,
+                description: "How long was spent on it, like 2h, 30m or 1h30m",
+            },
+            Argument {
+                name: "date",
+                short: None,
+                value: Some("DATE"),
+                required: false,
+                description: "When the time started being spent, instead of it ending now",
+            },
+        ],
+        examples: &[
+            "log --id 3 --time 45m",
+            "log -i 1.2 -t 1h30m --date yesterday 14:00",
+        ],
+    },
+    Command {
+        name: "report",
+        aliases: &[],
+        operands: None,
+        summary: "Show how much time was spent on each task or tag",
+        arguments: &[
+            Argument {
+                name: "from",
+                short: None,
+                value: Some("DATE"),
+                required: false,
+                description: "Only count time spent from this date, instead of the last week",
+            },
+            Argument {
+                name: "to",
+                short: None,
+                value: Some("DATE"),
+                required: false,
+                description: "Only count time spent before this date, instead of up until now",
+            },
+            Argument {
+                name: "by",
+                short: None,
+                value: Some("GROUPING"),
+                required: false,
+                description: "Add up the time for each task or for each tag, defaults to task",
+            },
+        ],
+        examples: &[
+            "report",
+            "report --from -1m --by tag",
+            "report --from 2023-01-01 --to 2023-02-01",
+        ],
+    },
+    Command {
+        name: "undo",
+        aliases: &[],
+        operands: None,
+        summary: "Undo the last change to your tasks, whether it was made here or in the TUI",
+        arguments: &[],
+        examples: &["undo"],
+    },
+    Command {
+        name: "redo",
+        aliases: &[],
+        operands: None,
+        summary: "Redo the last change that was undone",
+        arguments: &[],
+        examples: &["redo"],
+    },
+    Command {
+        name: "lists",
+        aliases: &[],
+        operands: Some("[create <NAME> | rename <NAME> <NEW NAME> | delete <NAME>]"),
+        summary: "Show your task lists, or create, rename or delete one",
+        arguments: &[],
+        examples: &[
+            "lists",
+            "lists create work",
+            "lists rename work team",
+            "--list team add --name Plan the sprint",
+        ],
+    },
+    Command {
+        name: "config",
+        aliases: &[],
+        operands: None,
+        summary: "Print the config being used, including every default that wasn't changed",
+        arguments: &[],
+        examples: &["config", "config --config ~/work.toml"],
+    },
+    Command {
+        name: "help",
+        aliases: &[],
+        operands: Some("[COMMAND]"),
+        summary: "Show which commands there are, or how to use one of them",
+        arguments: &[],
+        examples: &["help", "help add"],
+    },
+];
+
+/// Finds a command by its name or one of its aliases
+pub fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS
+        .iter()
+        .find(|command| command.name == name || command.aliases.contains(&name))
+}
+
+impl Argument {
+    /// How the argument is written in usage text, like `-i, --id <ID>`
+    fn signature(&self) -> String {
+        let mut signature = match self.short {
+            Some(short) => format!("-{}, --{}", short, self.name),
+            None => format!("--{}", self.name),
+        };
+        if let Some(value) = self.value {
+            signature.push_str(&format!(" <{}>", value));
+        }
+        signature
+    }
+}
+
+impl Command {
+    /// Finds one of the arguments this command accepts by its name or short name
+    fn find_argument(&self, name: &str) -> Option<&'static Argument> {
+        self.arguments
+            .iter()
+            .chain(GLOBAL_ARGUMENTS)
+            .find(|argument| argument.name == name || argument.short == Some(name))
+    }
+
+    /// A one-line summary of how to run the command, like `tasklist move --id <ID> [--top]`
+    pub fn usage(&self, program: &str) -> String {
+        let mut usage = format!("{} {}", program, self.name);
+        if let Some(operands) = self.operands {
+            usage.push_str(&format!(" {}", operands));
+        }
+        for argument in self.arguments {
+            let mut written = format!("--{}", argument.name);
+            if let Some(value) = argument.value {
+                written.push_str(&format!(" <{}>", value));
+            }
+            if argument.required {
+                usage.push_str(&format!(" {}", written));
+            } else {
+                usage.push_str(&format!(" [{}]", written));
+            }
+        }
+        usage
+    }
+
+    /// The full help for the command, shown by `help <command>` or `<command> --help`
+    pub fn help(&self, program: &str) -> String {
+        let mut help = self.summary.to_owned();
+        if !self.aliases.is_empty() {
+            help.push_str(&format!("\nAlso known as: {}", self.aliases.join(", ")));
+        }
+        help.push_str(&format!("\n\nUsage: {}\n", self.usage(program)));
+
+        let arguments: Vec<&Argument> = self.arguments.iter().chain(GLOBAL_ARGUMENTS).collect();
+        let signatures: Vec<String> = arguments
+            .iter()
+            .map(|argument| argument.signature())
+            .collect();
+        let width = signatures
+            .iter()
+            .map(|signature| signature.len())
+            .max()
+            .unwrap_or_default();
+
+        help.push_str("\nArguments:\n");
+        for (argument, signature) in arguments.iter().zip(signatures) {
+            help.push_str(&format!(
+                "  {:width$}  {}\n",
+                signature,
+                argument.description,
+                width = width
+            ));
+        }
+
+        if !self.examples.is_empty() {
+            help.push_str("\nExamples:\n");
+            for example in self.examples {
+                help.push_str(&format!("  {} {}\n", program, example));
+            }
+        }
+
+        help.trim_end().to_owned()
+    }
+}
+
+/// The help shown by `help` on its own, listing every command
+pub fn overview(program: &str) -> String {
+    let names: Vec<String> = COMMANDS
+        .iter()
+        .map(|command| {
+            std::iter::once(command.name)
+                .chain(command.aliases.iter().copied())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect();
+    let width = names
+        .iter()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or_default();
+
+    let mut overview = format!(
+        "TeaL, a task list for your terminal\n\nUsage: {} <COMMAND> [ARGUMENTS]\nRun {} without a command to manage your tasks interactively\n\nCommands:\n",
+        program, program
+    );
+    for (command, names) in COMMANDS.iter().zip(names) {
+        overview.push_str(&format!(
+            "  {:width$}  {}\n",
+            names,
+            command.summary,
+            width = width
+        ));
+    }
+    overview.push_str(
+        "\nCommands which print tasks can print them as JSON with --format json, or --format jsonl \
+        for one task per line. Each task is an object with its id, description, complete, parent, \
+        due, scheduled, priority, notes, created, updated, completed and recurrence (how often it \
+        repeats, or null), along with its level (how many parents it has) and \
+        parent_ids (its parents' ids, starting from the top). Tasks in the trash also have deleted, \
+        when they were removed. Dates are unix timestamps or null\n",
+    );
+    overview.push_str(
+        "\nSettings are read from config.toml in TeaL's config directory, or from the file given with \
+        --config or the TEAL_CONFIG environment variable. Run the config command to see them all\n",
+    );
+    overview.push_str(
+        "\nYour tasks are kept in the default list unless another is picked with --list, or a \
+        database file is picked with --db. Run the lists command to see them all\n",
+    );
+    overview.push_str(&format!(
+        "\nRun '{} help <COMMAND>' or '{} <COMMAND> --help' to find out more about a command",
+        program, program
+    ));
+    overview
+}
+
+/// Checks the arguments passed to a command against the arguments it accepts, and replaces any short
+/// names with the full name so that commands only need to look arguments up by their full name
+pub fn resolve_arguments(command: &Command, args: &mut Arguments) -> Result<(), String> {
+    for (name, values) in std::mem::take(&mut args.args) {
+        let argument = command
+            .find_argument(name)
+            .ok_or_else(|| unknown_argument(name))?;
+        if argument.value.is_none() {
+            return Err(format!("--{} doesn't take a value", argument.name));
+        }
+        args.args.entry(argument.name).or_default().extend(values);
+    }
+
+    for name in std::mem::take(&mut args.flags) {
+        let argument = command
+            .find_argument(name)
+            .ok_or_else(|| unknown_argument(name))?;
+        if argument.value.is_some() {
+            return Err(format!("--{} needs a value", argument.name));
+        }
+        args.flags.insert(argument.name);
+    }
+
+    Ok(())
+}
+
+fn unknown_argument(name: &str) -> String {
+    if name.chars().count() == 1 {
+        format!("Unknown argument: -{}", name)
+    } else {
+        format!("Unknown argument: --{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::argument_parser::parse_args;
+    use super::*;
+
+    #[test]
+    fn command_names_are_unique_test() {
+        let mut names: Vec<&str> = COMMANDS
+            .iter()
+            .flat_map(|command| {
+                std::iter::once(command.name).chain(command.aliases.iter().copied())
+            })
+            .collect();
+        let name_count = names.len();
+        names.sort();
+        names.dedup();
+
+        assert_eq!(names.len(), name_count);
+        assert_eq!(find_command("ls").unwrap().name, "list");
+        assert!(find_command("frobnicate").is_none());
+    }
+
+    #[test]
+    fn resolve_arguments_test() {
+        let command = find_command("done").unwrap();
+
+        let mut args = parse_args(vec!["tasklist", "done", "-ri", "1", "2", "--raw"]).unwrap();
+        resolve_arguments(command, &mut args).unwrap();
+        assert_eq!(args.args.get("id"), Some(&vec!["1", "2"]));
+        assert!(args.flags.contains("recursive"));
+        assert!(args.flags.contains("raw"));
+        assert!(!args.flags.contains("r"));
+
+        let mut args = parse_args(vec!["tasklist", "done", "--id", "1", "--name", "a"]).unwrap();
+        assert_eq!(
+            resolve_arguments(command, &mut args),
+            Err("Unknown argument: --name".to_owned())
+        );
+
+        let mut args = parse_args(vec!["tasklist", "done", "--id"]).unwrap();
+        assert_eq!(
+            resolve_arguments(command, &mut args),
+            Err("--id needs a value".to_owned())
+        );
+
+        let mut args = parse_args(vec!["tasklist", "done", "-i", "1", "--raw", "yes"]).unwrap();
+        assert_eq!(
+            resolve_arguments(command, &mut args),
+            Err("--raw doesn't take a value".to_owned())
+        );
+    }
+
+    #[test]
+    fn help_lists_every_argument_test() {
+        for command in COMMANDS {
+            let help = command.help("tasklist");
+            for argument in command.arguments.iter().chain(GLOBAL_ARGUMENTS) {
+                assert!(help.contains(&format!("--{}", argument.name)));
+            }
+        }
+        assert!(overview("tasklist").contains("move, mv"));
+    }
+}
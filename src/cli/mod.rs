@@ -1,41 +1,467 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::Local;
 use eyre::Result;
 
 use crate::{
-    database::{self, Database, FlatTaskTreeElement, ToFlatTaskTreeElement},
-    sorting,
+    config::{self, Config},
+    database::{
+        self, parse_priority, BlockTaskError, Database, FlatTaskTreeElement, MoveTaskError,
+        NewTask, Task, TaskUpdate, ToFlatTaskTreeElement,
+    },
+    dates,
+    durations::{format_duration, parse_duration},
+    editor,
+    keybindings::Keymap,
+    lists::TaskLists,
+    recurrence::Recurrence,
+    sorting::{self, SortOrder},
+    tags::{normalize_tag, split_tags},
 };
 
-use self::argument_parser::parse_ids;
-use self::rendering::render_table;
+pub use self::argument_parser::take_global_argument;
+use self::argument_parser::{parse_ids, Arguments};
+use self::commands::Command;
+use self::rendering::{render_tasks, OutputFormat};
 
 mod argument_parser;
+mod commands;
 mod rendering;
 
-pub async fn run(mut db: Database, args: Vec<String>) -> Result<()> {
-    let args = argument_parser::parse_args(args.iter().map(String::as_str).collect())?;
-    match args.subcommand[..] {
-        ["list" | "ls"] => {
+/// Parses the date given for an argument such as `--due`, returning `None` if it wasn't passed
+fn parse_date_argument(args: &Arguments, name: &str) -> Result<Option<i64>, String> {
+    match args.args.get(name) {
+        Some(date) => dates::parse_date(&date.join(" "), Local::now()).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Parses a date given to change a task with, such as `edit --due`. Passing `none` removes the date,
+/// and if the argument wasn't passed at all the date is left as it is
+fn parse_date_update(args: &Arguments, name: &str) -> Result<Option<Option<i64>>, String> {
+    match args.args.get(name) {
+        Some(date) if date.join(" ").eq_ignore_ascii_case("none") => Ok(Some(None)),
+        _ => parse_date_argument(args, name).map(|date| date.map(Some)),
+    }
+}
+
+/// Parses the rule given for `--repeat`. Passing `none` stops a task from repeating, and if the
+/// argument wasn't passed at all the rule is left as it is
+fn parse_recurrence_update(args: &Arguments) -> Result<Option<Option<Recurrence>>, String> {
+    match args.args.get("repeat").map(|rule| rule.join(" ")) {
+        Some(rule) if rule.eq_ignore_ascii_case("none") => Ok(Some(None)),
+        Some(rule) => Recurrence::parse(&rule).map(|rule| Some(Some(rule))),
+        None => Ok(None),
+    }
+}
+
+/// Parses the value given for `--complete-with-subtasks`. Passing `default` makes the task follow the
+/// config again, and if the argument wasn't passed at all the setting is left as it is
+fn parse_complete_with_subtasks_update(args: &Arguments) -> Result<Option<Option<bool>>, String> {
+    let value = match args.args.get("complete-with-subtasks") {
+        Some(value) => value.join(" "),
+        None => return Ok(None),
+    };
+
+    match value.to_lowercase().as_str() {
+        "yes" => Ok(Some(Some(true))),
+        "no" => Ok(Some(Some(false))),
+        "default" => Ok(Some(None)),
+        _ => Err(format!(
+            "Invalid value for --complete-with-subtasks: {}, expected yes, no or default",
+            value
+        )),
+    }
+}
+
+/// Parses the length of time given for `--estimate`. Passing `none` removes the estimate, and if the
+/// argument wasn't passed at all the estimate is left as it is
+fn parse_estimate_update(args: &Arguments) -> Result<Option<Option<i64>>, String> {
+    match args.args.get("estimate").map(|estimate| estimate.join(" ")) {
+        Some(estimate) if estimate.eq_ignore_ascii_case("none") => Ok(Some(None)),
+        Some(estimate) => parse_duration(&estimate).map(|estimate| Some(Some(estimate))),
+        None => Ok(None),
+    }
+}
+
+/// The tasks which aren't done yet and are blocking a task, or any of its subtasks if `recursive`.
+/// Blockers which are subtasks of the task are left out then, as they'd be done along with it
+async fn open_blockers_of(
+    db: &mut Database,
+    blockers: &HashMap<i64, Vec<i64>>,
+    task_id: i64,
+    recursive: bool,
+) -> Result<Vec<i64>> {
+    let task_ids: Vec<i64> = if recursive {
+        match db.list_subtasks(task_id).await {
+            Ok(task_tree) => Vec::<FlatTaskTreeElement>::from(task_tree)
+                .iter()
+                .map(|element| element.task.id)
+                .collect(),
+            // The task doesn't exist, which is reported when it's marked as done
+            Err(_) => vec![],
+        }
+    } else {
+        vec![task_id]
+    };
+
+    let mut open_blockers: Vec<i64> = task_ids
+        .iter()
+        .filter_map(|id| blockers.get(id))
+        .flatten()
+        .copied()
+        .filter(|blocker| !task_ids.contains(blocker))
+        .collect();
+    open_blockers.sort_unstable();
+    open_blockers.dedup();
+    Ok(open_blockers)
+}
+
+/// Prints tasks in the format the user asked for. As the tasks only know about the parents in their
+/// own tree, this first fills in the rest of their parents, so that each task's `parent_ids` go all
+/// the way up to the top level and its `level` is how deeply it's nested. How many of each task's
+/// subtasks are done is filled in too, including any which weren't listed, as is how much work is
+/// estimated to be left on tasks which weren't listed along with their subtasks
+async fn print_tasks(
+    db: &mut Database,
+    mut tasks: Vec<FlatTaskTreeElement>,
+    format: OutputFormat,
+) -> Result<()> {
+    let progress = db.list_progress().await?;
+    let mut tree_parent_ids = vec![];
+    for element in &mut tasks {
+        element.progress = progress.get(&element.task.id).copied();
+        if element.remaining_estimate.is_none() {
+            // Tasks in the trash don't have any subtasks to look up
+            element.remaining_estimate = db
+                .list_subtasks(element.task.id)
+                .await
+                .ok()
+                .map(|task_tree| task_tree.remaining_estimate());
+        }
+        if element.level == 0 {
+            // This is the root of a tree, so the parents of every task below it start the same way
+            tree_parent_ids = match element.task.parent {
+                Some(parent) => db.list_path(parent).await?,
+                None => vec![],
+            };
+            element.parent_ids = tree_parent_ids.clone();
+        } else {
+            element.parent_ids = tree_parent_ids
+                .iter()
+                .chain(&element.parent_ids)
+                .copied()
+                .collect();
+        }
+        element.level = element.parent_ids.len();
+    }
+
+    println!("{}", render_tasks(tasks, format)?);
+    Ok(())
+}
+
+pub async fn run(db: &mut Database, lists: &TaskLists, args: Vec<String>) -> Result<()> {
+    let command_line = args[1..].join(" ");
+    let mut args = argument_parser::parse_args(args.iter().map(String::as_str).collect())?;
+
+    let command = match args
+        .subcommand
+        .first()
+        .map(|name| commands::find_command(name))
+    {
+        None => {
+            println!("{}", commands::overview(args.command));
+            return Ok(());
+        }
+        Some(Some(command)) if command.operands.is_some() || args.subcommand.len() == 1 => command,
+        Some(_) => {
+            println!(
+                "Command '{}' doesn't exist, please run '{} help' for help",
+                args.subcommand.join(" "),
+                args.command
+            );
+            return Ok(());
+        }
+    };
+
+    if let Err(error) = commands::resolve_arguments(command, &mut args) {
+        println!(
+            "{}, please run '{} help {}' for help",
+            error, args.command, command.name
+        );
+        return Ok(());
+    }
+
+    if args.flags.contains("help") {
+        println!("{}", command.help(args.command));
+        return Ok(());
+    }
+
+    let format = match args.args.get("format") {
+        Some(format) => match OutputFormat::parse(&format.join(" ")) {
+            Ok(format) => format,
+            Err(error) => {
+                println!(
+                    "{}, please run '{} help {}' for help",
+                    error, args.command, command.name
+                );
+                return Ok(());
+            }
+        },
+        None if args.flags.contains("raw") => OutputFormat::Raw,
+        None => OutputFormat::Table,
+    };
+
+    match command.name {
+        "undo" | "redo" => {
+            let undoing = command.name == "undo";
+            match (undoing, db.undo_or_redo(undoing).await?) {
+                (true, Some(description)) => println!("Undid '{}'", description),
+                (false, Some(description)) => println!("Redid '{}'", description),
+                (true, None) => println!("There's nothing to undo"),
+                (false, None) => println!("There's nothing to redo"),
+            }
+        }
+        _ => {
+            // Everything a command changes is undone together, as one change named after the
+            // command line
+            db.begin_change(&command_line).await?;
+            let result = run_command(db, lists, &args, command, format).await;
+            db.end_change().await?;
+            result?;
+        }
+    }
+    Ok(())
+}
+
+async fn run_command(
+    db: &mut Database,
+    lists: &TaskLists,
+    args: &Arguments<'_>,
+    command: &Command,
+    format: OutputFormat,
+) -> Result<()> {
+    match command.name {
+        "lists" => {
+            let result = match args.subcommand[1..] {
+                [] => {
+                    let names = lists.names()?;
+                    let width = names.iter().map(String::len).max().unwrap_or_default();
+                    for name in names {
+                        println!(
+                            "{} {:width$}  {}",
+                            if lists.current.as_ref() == Some(&name) {
+                                "*"
+                            } else {
+                                " "
+                            },
+                            name,
+                            lists.path(&name)?.display(),
+                            width = width
+                        );
+                    }
+                    Ok(())
+                }
+                ["create", name] => match lists.create(name) {
+                    Ok(path) => {
+                        let mut new_db =
+                            Database::new(Some(path.to_string_lossy().into_owned())).await?;
+                        new_db.setup().await?;
+                        new_db.close().await?;
+                        println!(
+                            "Created the {} list, run '{} --list {}' to use it",
+                            name, args.command, name
+                        );
+                        Ok(())
+                    }
+                    Err(error) => Err(error.to_string()),
+                },
+                ["rename" | "delete", name, ..] if lists.current.as_deref() == Some(name) => Err(
+                    format!("The {} list is being used, so it can't be changed", name),
+                ),
+                ["rename", name, new_name] => lists
+                    .rename(name, new_name)
+                    .map(|_| println!("Renamed the {} list to {}", name, new_name))
+                    .map_err(|error| error.to_string()),
+                ["delete", name] => lists
+                    .delete(name)
+                    .map(|_| println!("Deleted the {} list along with all of its tasks", name))
+                    .map_err(|error| error.to_string()),
+                _ => Err(format!(
+                    "Invalid command: lists {}",
+                    args.subcommand[1..].join(" ")
+                )),
+            };
+
+            if let Err(error) = result {
+                println!(
+                    "{}, please run '{} help lists' for help",
+                    error, args.command
+                );
+            }
+        }
+        "config" => {
+            let mut config = config::get().clone();
+            // Show the database that's actually being used, which might be from --db or --list
+            config.database = Some(lists.current_database.clone());
+            config.keys = Keymap::merged_keys(&config.keys);
+
+            match &config.source {
+                Some(source) => println!("# Read from {}\n", source.display()),
+                None => println!(
+                    "# No config file was found, so these are the defaults. Put any of them in {} \
+                    to change them\n",
+                    Config::path(None).0.display()
+                ),
+            }
+            print!("{}", config.to_toml()?);
+        }
+        "help" => match args.subcommand[1..] {
+            [] => println!("{}", commands::overview(args.command)),
+            [name] => match commands::find_command(name) {
+                Some(command) => println!("{}", command.help(args.command)),
+                None => println!(
+                    "Command '{}' doesn't exist, please run '{} help' for help",
+                    name, args.command
+                ),
+            },
+            _ => println!(
+                "Only one command can be looked up at a time, please run '{} help help' for help",
+                args.command
+            ),
+        },
+        "list" => {
             let search = args.args.get("search");
+            let sort_order = match args.args.get("sort") {
+                Some(sort_order) => match SortOrder::parse(&sort_order.join(" ")) {
+                    Ok(sort_order) => Some(sort_order),
+                    Err(error) => {
+                        println!(
+                            "{}, please run '{} help list' for help",
+                            error, args.command
+                        );
+                        return Ok(());
+                    }
+                },
+                // Tasks are already listed by ID, and searches by relevance unless asked otherwise
+                None => Some(config::get().list.sort).filter(|order| *order != SortOrder::Id),
+            };
+            let (created_since, completed_since) = match (
+                parse_date_argument(args, "created-since"),
+                parse_date_argument(args, "completed-since"),
+            ) {
+                (Ok(created_since), Ok(completed_since)) => (created_since, completed_since),
+                (Err(error), _) | (_, Err(error)) => {
+                    println!(
+                        "{}, please run '{} help list' for help",
+                        error, args.command
+                    );
+                    return Ok(());
+                }
+            };
+            // Asking for what was done means showing it even if the config hides tasks that are done
+            let hide_done = config::get().list.hide_done
+                && !args.flags.contains("all")
+                && completed_since.is_none();
 
-            let mut tasks = db.list_tasks(false).await?;
+            let (blocked_only, ready_only) =
+                (args.flags.contains("blocked"), args.flags.contains("ready"));
+            if blocked_only && ready_only {
+                println!(
+                    "A task can't be blocked and ready at the same time, please run '{} help list' for help",
+                    args.command
+                );
+                return Ok(());
+            }
+            let blockers = db.list_open_blockers().await?;
+
+            let tags = args.args.get("tag").map(|tags| {
+                tags.iter()
+                    .map(|tag| normalize_tag(tag))
+                    .collect::<Vec<_>>()
+            });
+
+            let mut tasks = match tags {
+                // Tagged subtasks are shown too, so that they aren't hidden by untagged parents
+                Some(ref tags) => {
+                    let mut tagged_tasks: Option<Vec<Task>> = None;
+                    for tag in tags {
+                        let tasks_with_tag = db.list_tasks_with_tag(tag).await?;
+                        tagged_tasks = Some(match tagged_tasks {
+                            None => tasks_with_tag,
+                            Some(tagged_tasks) => tagged_tasks
+                                .into_iter()
+                                .filter(|task| {
+                                    tasks_with_tag
+                                        .iter()
+                                        .any(|task_with_tag| task_with_tag.id == task.id)
+                                })
+                                .collect(),
+                        });
+                    }
+                    tagged_tasks.unwrap_or_default()
+                }
+                // As with tags, subtasks are checked too so that they aren't hidden by their parents
+                None if created_since.is_some()
+                    || completed_since.is_some()
+                    || blocked_only
+                    || ready_only =>
+                {
+                    db.list_tasks(true).await?
+                }
+                None => db.list_tasks(false).await?,
+            };
+            tasks.retain(|task| {
+                created_since
+                    .is_none_or(|since| task.created.is_some_and(|created| created >= since))
+                    && completed_since.is_none_or(|since| {
+                        task.completed.is_some_and(|completed| completed >= since)
+                    })
+                    && (!blocked_only || blockers.contains_key(&task.id))
+                    && (!ready_only || !(task.complete || blockers.contains_key(&task.id)))
+            });
 
             if let Some(term) = search {
-                println!("Searching for '{}'", &term.join(" "));
+                if format.is_for_people() {
+                    println!("Searching for '{}'", &term.join(" "));
+                }
                 tasks = sorting::search(&term.join(" "), tasks);
             }
 
+            if hide_done {
+                tasks.retain(|task| !task.complete);
+            }
+
+            if let Some(sort_order) = sort_order {
+                tasks = sorting::sort(tasks, sort_order);
+            }
+
             let mut task_tree_elements: Vec<database::FlatTaskTreeElement> = vec![];
+            let mut listed_task_ids: HashSet<i64> = HashSet::new();
 
             for task in tasks {
-                task_tree_elements.append(&mut db.list_subtasks(task.id).await?.into());
-            }
+                if listed_task_ids.contains(&task.id) {
+                    // This has already been listed as a subtask of another matching task
+                    continue;
+                }
 
-            let table_string = render_table(task_tree_elements, args.flags.get("raw").is_some());
+                let mut task_tree = db.list_subtasks(task.id).await?;
+                if hide_done {
+                    sorting::remove_done(&mut task_tree);
+                }
+                if let Some(sort_order) = sort_order {
+                    sorting::sort_tree(&mut task_tree, sort_order);
+                }
+                let mut flat_task_tree: Vec<FlatTaskTreeElement> = task_tree.into();
+                listed_task_ids.extend(flat_task_tree.iter().map(|element| element.task.id));
+                task_tree_elements.append(&mut flat_task_tree);
+            }
 
-            println!("{}", table_string);
+            print_tasks(db, task_tree_elements, format).await?;
         }
-        ["add" | "create"] => {
-            let task_name = args.args.get("name").unwrap_or(&Vec::default()).join(" ");
+        "add" => {
+            let (task_name, tags) =
+                split_tags(&args.args.get("name").unwrap_or(&Vec::default()).join(" "));
             let parent = args.args.get("parent");
 
             let parent_id = if parent.is_some() {
@@ -67,26 +493,63 @@ pub async fn run(mut db: Database, args: Vec<String>) -> Result<()> {
                 return Ok(());
             }
 
-            let task = db.add_task(task_name.as_str(), parent_id).await;
+            let priority = match args.args.get("priority") {
+                Some(priority) => match parse_priority(&priority.join(" ")) {
+                    Ok(priority) => priority,
+                    Err(error) => {
+                        println!("{}, please run '{} help add' for help", error, args.command);
+                        return Ok(());
+                    }
+                },
+                None => 0,
+            };
 
-            match task {
-                Ok(task) => {
-                    println!(
-                        "{}",
-                        render_table(
-                            vec![FlatTaskTreeElement {
-                                level: 0,
-                                last_under_parent: false,
-                                task,
-                                parent_ids: match parent_id {
-                                    Some(parent_id) => vec![parent_id],
-                                    None => vec![],
-                                }
-                            }],
-                            args.flags.get("raw").is_some()
-                        )
-                    )
+            let (due, scheduled) = match (
+                parse_date_argument(args, "due"),
+                parse_date_argument(args, "scheduled"),
+            ) {
+                (Ok(due), Ok(scheduled)) => (due, scheduled),
+                (Err(error), _) | (_, Err(error)) => {
+                    println!("{}, please run '{} help add' for help", error, args.command);
+                    return Ok(());
                 }
+            };
+            let (recurrence, complete_with_subtasks, estimate) = match (
+                parse_recurrence_update(args),
+                parse_complete_with_subtasks_update(args),
+                parse_estimate_update(args),
+            ) {
+                (Ok(recurrence), Ok(complete_with_subtasks), Ok(estimate)) => (
+                    recurrence.flatten(),
+                    complete_with_subtasks.flatten(),
+                    estimate.flatten(),
+                ),
+                (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => {
+                    println!("{}, please run '{} help add' for help", error, args.command);
+                    return Ok(());
+                }
+            };
+
+            let task = db
+                .add_task(&NewTask {
+                    due,
+                    scheduled,
+                    priority,
+                    tags,
+                    recurrence,
+                    complete_with_subtasks,
+                    estimate,
+                    notes: args
+                        .args
+                        .get("notes")
+                        .map(|notes| notes.join(" "))
+                        .unwrap_or_default(),
+                    ..NewTask::new(&task_name, parent_id)
+                })
+                .await;
+
+            match task {
+                Ok(task) => print_tasks(db, vec![task.into()], format).await?,
                 Err(error) => {
                     if let sqlx::Error::Database(error) = error {
                         match error.message() {
@@ -102,12 +565,13 @@ pub async fn run(mut db: Database, args: Vec<String>) -> Result<()> {
                 }
             };
         }
-        ["remove" | "del"] => {
-            let task_ids = args.args.get("id").or_else(|| args.args.get("i"));
+        "remove" => {
+            let task_ids = args.args.get("id");
             let parsed_task_ids = parse_ids(task_ids);
 
             match parsed_task_ids {
                 Ok(parsed_task_ids) => {
+                    let mut deleted_task_elements = vec![];
                     for id in parsed_task_ids {
                         let deleted_tasks = db.remove_task(id).await?;
                         let number_of_deleted_tasks = deleted_tasks.len();
@@ -122,21 +586,847 @@ pub async fn run(mut db: Database, args: Vec<String>) -> Result<()> {
                                 return Ok(());
                             }
                         };
-                        let table = render_table(flat_task_tree, args.flags.get("raw").is_some());
-                        println!("Deleted {} tasks:", number_of_deleted_tasks);
-                        println!("{}", table);
+                        if format.is_for_people() {
+                            println!("Moved {} tasks to the trash:", number_of_deleted_tasks);
+                            print_tasks(db, flat_task_tree, format).await?;
+                        } else {
+                            deleted_task_elements.extend(flat_task_tree);
+                        }
+                    }
+
+                    if !format.is_for_people() {
+                        print_tasks(db, deleted_task_elements, format).await?;
                     }
                 }
                 Err(error) => println!("{}, please run {} for help", error, args.command),
             }
         }
-        _ => {
-            println!(
-                "Command '{}' doesn't exist, please run '{} help' for help",
-                args.subcommand.join(" "),
+        "trash" => {
+            let trash = db.list_trash().await?;
+            if trash.is_empty() && format.is_for_people() {
+                println!("The trash is empty");
+                return Ok(());
+            }
+
+            let mut trashed_task_elements = vec![];
+            for task_tree in trash {
+                let deleted = task_tree.deleted.unwrap_or_default();
+                let flat_task_tree: Vec<FlatTaskTreeElement> = task_tree.into();
+                if format.is_for_people() {
+                    println!("Removed {}:", dates::format_date(deleted));
+                    print_tasks(db, flat_task_tree, format).await?;
+                } else {
+                    trashed_task_elements.extend(flat_task_tree);
+                }
+            }
+
+            if !format.is_for_people() {
+                print_tasks(db, trashed_task_elements, format).await?;
+            }
+        }
+        "restore" => {
+            let parsed_task_ids = match parse_ids(args.args.get("id")) {
+                Ok(parsed_task_ids) => parsed_task_ids,
+                Err(error) => {
+                    println!(
+                        "{}, please run '{} help restore' for help",
+                        error, args.command
+                    );
+                    return Ok(());
+                }
+            };
+
+            let mut restored_task_elements = vec![];
+            for id in parsed_task_ids {
+                let restored_tasks = db.restore_task(id).await?;
+                if restored_tasks.is_empty() {
+                    println!(
+                        "Task {} isn't in the trash, please run '{} trash' to see what is",
+                        id, args.command
+                    );
+                    return Ok(());
+                }
+                restored_task_elements.extend(restored_tasks.try_to_flat_task_tree_element()?);
+            }
+
+            print_tasks(db, restored_task_elements, format).await?;
+        }
+        "purge" => {
+            let removed_before = match args.args.get("older-than") {
+                Some(days) => match days.join(" ").parse::<u32>() {
+                    Ok(days) => Local::now().timestamp() - i64::from(days) * 24 * 60 * 60,
+                    Err(_) => {
+                        println!(
+                            "Invalid number of days: {}, please run '{} help purge' for help",
+                            days.join(" "),
+                            args.command
+                        );
+                        return Ok(());
+                    }
+                },
+                None => i64::MAX,
+            };
+
+            let purged_tasks = db.purge_trash(removed_before).await?;
+            println!("Deleted {} tasks for good", purged_tasks.len());
+        }
+        subcommand @ ("done" | "undone") => {
+            let completed = subcommand == "done";
+            let task_ids = args.args.get("id");
+            let parsed_task_ids = match parse_ids(task_ids) {
+                Ok(parsed_task_ids) => parsed_task_ids,
+                Err(error) => {
+                    println!(
+                        "{}, please run '{} help {}' for help",
+                        error, args.command, subcommand
+                    );
+                    return Ok(());
+                }
+            };
+            let recursive = args.flags.contains("recursive");
+            let blockers = if completed && !args.flags.contains("force") {
+                db.list_open_blockers().await?
+            } else {
+                HashMap::new()
+            };
+
+            let mut updated_task_elements = vec![];
+            for id in parsed_task_ids {
+                let open_blockers = open_blockers_of(db, &blockers, id, recursive).await?;
+                if !open_blockers.is_empty() {
+                    println!(
+                        "Task {} is blocked by {} which {} done yet, run '{} done --force --id {}' to mark it as done anyway",
+                        id,
+                        open_blockers
+                            .iter()
+                            .map(|blocker| format!("task {}", blocker))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        if open_blockers.len() == 1 { "isn't" } else { "aren't" },
+                        args.command,
+                        id
+                    );
+                    continue;
+                }
+
+                let (flat_task_tree, next, parents) = if recursive {
+                    let (updated_tasks, next, parents) =
+                        db.set_subtree_completion(id, completed).await?;
+                    match updated_tasks.try_to_flat_task_tree_element() {
+                        Ok(flat_task_tree) => (flat_task_tree, next, parents),
+                        Err(_) => {
+                            println!(
+                                "Task {} doesn't exist, please run '{} list' to view all of your tasks",
+                                id, args.command
+                            );
+                            return Ok(());
+                        }
+                    }
+                } else {
+                    match db.set_completion(id, completed).await {
+                        Ok((task, next, parents)) => (vec![task.into()], next, parents),
+                        Err(sqlx::Error::RowNotFound) => {
+                            println!(
+                                "Task {} doesn't exist, please run '{} list' to view all of your tasks",
+                                id, args.command
+                            );
+                            return Ok(());
+                        }
+                        Err(error) => return Err(error.into()),
+                    }
+                };
+
+                if !format.is_for_people() {
+                    updated_task_elements.extend(flat_task_tree);
+                    updated_task_elements.extend(next.map(FlatTaskTreeElement::from));
+                    updated_task_elements
+                        .extend(parents.into_iter().map(FlatTaskTreeElement::from));
+                    continue;
+                }
+
+                println!(
+                    "Marked {} {} as {}:",
+                    flat_task_tree.len(),
+                    if flat_task_tree.len() == 1 {
+                        "task"
+                    } else {
+                        "tasks"
+                    },
+                    if completed { "done" } else { "not done" }
+                );
+                print_tasks(db, flat_task_tree, format).await?;
+
+                if let Some(next) = next {
+                    println!(
+                        "Task {} repeats {}, so it was added again for next time:",
+                        id,
+                        next.recurrence.as_deref().unwrap_or_default()
+                    );
+                    print_tasks(db, vec![next.into()], format).await?;
+                }
+
+                if !parents.is_empty() {
+                    println!(
+                        "Marked {} {} as {} to match {} subtasks:",
+                        parents.len(),
+                        if parents.len() == 1 {
+                            "parent"
+                        } else {
+                            "parents"
+                        },
+                        if completed { "done" } else { "not done" },
+                        if parents.len() == 1 { "its" } else { "their" }
+                    );
+                    print_tasks(
+                        db,
+                        parents.into_iter().map(FlatTaskTreeElement::from).collect(),
+                        format,
+                    )
+                    .await?;
+                }
+            }
+
+            if !format.is_for_people() {
+                print_tasks(db, updated_task_elements, format).await?;
+            }
+        }
+        "priority" => {
+            let task_ids = args.args.get("id");
+            let parsed_task_ids = match parse_ids(task_ids) {
+                Ok(parsed_task_ids) => parsed_task_ids,
+                Err(error) => {
+                    println!(
+                        "{}, please run '{} help priority' for help",
+                        error, args.command
+                    );
+                    return Ok(());
+                }
+            };
+
+            let priority = match args.args.get("priority") {
+                Some(priority) => match parse_priority(&priority.join(" ")) {
+                    Ok(priority) => priority,
+                    Err(error) => {
+                        println!(
+                            "{}, please run '{} help priority' for help",
+                            error, args.command
+                        );
+                        return Ok(());
+                    }
+                },
+                None => {
+                    println!(
+                        "No priority provided, please run '{} help priority' for help",
+                        args.command
+                    );
+                    return Ok(());
+                }
+            };
+
+            let mut updated_tasks = vec![];
+            for id in parsed_task_ids {
+                let update = TaskUpdate {
+                    priority: Some(priority),
+                    ..Default::default()
+                };
+                match db.update_task(id, &update).await {
+                    Ok(task) => updated_tasks.push(task.into()),
+                    Err(sqlx::Error::RowNotFound) => {
+                        println!(
+                            "Task {} doesn't exist, please run '{} list' to view all of your tasks",
+                            id, args.command
+                        );
+                        return Ok(());
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+            }
+
+            print_tasks(db, updated_tasks, format).await?;
+        }
+        subcommand @ ("tag" | "untag") => {
+            let task_ids = args.args.get("id");
+            let parsed_task_ids = match parse_ids(task_ids) {
+                Ok(parsed_task_ids) => parsed_task_ids,
+                Err(error) => {
+                    println!(
+                        "{}, please run '{} help {}' for help",
+                        error, args.command, subcommand
+                    );
+                    return Ok(());
+                }
+            };
+
+            let tags = match args.args.get("tag") {
+                Some(tags) => tags
+                    .iter()
+                    .map(|tag| normalize_tag(tag))
+                    .collect::<Vec<_>>(),
+                None => {
+                    println!(
+                        "No tags provided, please run '{} help {}' for help",
+                        args.command, subcommand
+                    );
+                    return Ok(());
+                }
+            };
+
+            let mut tagged_tasks = vec![];
+            for id in parsed_task_ids {
+                for tag in &tags {
+                    if subcommand == "tag" {
+                        match db.tag_task(id, tag).await {
+                            Ok(()) if format.is_for_people() => {
+                                println!("Tagged task {} with {}", id, tag)
+                            }
+                            Ok(()) => {}
+                            Err(sqlx::Error::Database(error))
+                                if error.message() == "FOREIGN KEY constraint failed" =>
+                            {
+                                println!(
+                                    "Task {} doesn't exist, please run '{} list' to view all of your tasks",
+                                    id, args.command
+                                );
+                                return Ok(());
+                            }
+                            Err(error) => return Err(error.into()),
+                        }
+                    } else if db.untag_task(id, tag).await? {
+                        if format.is_for_people() {
+                            println!("Removed {} from task {}", tag, id);
+                        }
+                    } else if format.is_for_people() {
+                        println!("Task {} isn't tagged with {}", id, tag);
+                    }
+                }
+
+                if !format.is_for_people() {
+                    match db.get_task(id).await {
+                        Ok(task) => tagged_tasks.push(task.into()),
+                        Err(sqlx::Error::RowNotFound) => {
+                            println!(
+                                "Task {} doesn't exist, please run '{} list' to view all of your tasks",
+                                id, args.command
+                            );
+                            return Ok(());
+                        }
+                        Err(error) => return Err(error.into()),
+                    }
+                }
+            }
+
+            if !format.is_for_people() {
+                print_tasks(db, tagged_tasks, format).await?;
+            }
+        }
+        subcommand @ ("block" | "unblock") => {
+            let task_ids = args.args.get("id");
+            let (parsed_task_ids, blocker_ids) =
+                match (parse_ids(task_ids), parse_ids(args.args.get("by"))) {
+                    (Ok(parsed_task_ids), Ok(blocker_ids)) => (parsed_task_ids, blocker_ids),
+                    (Err(error), _) | (_, Err(error)) => {
+                        println!(
+                            "{}, please run '{} help {}' for help",
+                            error, args.command, subcommand
+                        );
+                        return Ok(());
+                    }
+                };
+
+            let mut changed_tasks = vec![];
+            for id in parsed_task_ids {
+                for &blocker in &blocker_ids {
+                    if subcommand == "block" {
+                        match db.block_task(id, blocker).await {
+                            Ok(()) if format.is_for_people() => {
+                                println!("Task {} is now blocked by task {}", id, blocker)
+                            }
+                            Ok(()) => {}
+                            Err(error @ BlockTaskError::Cycle) => {
+                                println!("{}", error);
+                                return Ok(());
+                            }
+                            Err(BlockTaskError::Database(sqlx::Error::RowNotFound)) => {
+                                println!(
+                                    "Task {} or task {} doesn't exist, please run '{} list' to view all of your tasks",
+                                    id, blocker, args.command
+                                );
+                                return Ok(());
+                            }
+                            Err(BlockTaskError::Database(error)) => return Err(error.into()),
+                        }
+                    } else if db.unblock_task(id, blocker).await? {
+                        if format.is_for_people() {
+                            println!("Task {} is no longer blocked by task {}", id, blocker);
+                        }
+                    } else if format.is_for_people() {
+                        println!("Task {} isn't blocked by task {}", id, blocker);
+                    }
+                }
+
+                if !format.is_for_people() {
+                    match db.get_task(id).await {
+                        Ok(task) => changed_tasks.push(task.into()),
+                        Err(sqlx::Error::RowNotFound) => {
+                            println!(
+                                "Task {} doesn't exist, please run '{} list' to view all of your tasks",
+                                id, args.command
+                            );
+                            return Ok(());
+                        }
+                        Err(error) => return Err(error.into()),
+                    }
+                }
+            }
+
+            if !format.is_for_people() {
+                print_tasks(db, changed_tasks, format).await?;
+            }
+        }
+        "edit" => {
+            let task_ids = args.args.get("id");
+            let task_id = match parse_ids(task_ids).as_deref() {
+                Ok([task_id]) => *task_id,
+                Ok(_) => {
+                    println!(
+                        "Only one task can be edited at a time, please run '{} help edit' for help",
+                        args.command
+                    );
+                    return Ok(());
+                }
+                Err(error) => {
+                    println!(
+                        "{}, please run '{} help edit' for help",
+                        error, args.command
+                    );
+                    return Ok(());
+                }
+            };
+
+            let (description, tags) = match args.args.get("name") {
+                Some(name) => {
+                    let (description, tags) = split_tags(&name.join(" "));
+                    if description.is_empty() {
+                        println!(
+                            "Task name cannot be empty, please run '{} help edit' for help",
+                            args.command
+                        );
+                        return Ok(());
+                    }
+                    (Some(description), tags)
+                }
+                None => (None, vec![]),
+            };
+
+            let priority = match args.args.get("priority").map(|priority| priority.join(" ")) {
+                Some(priority) => match parse_priority(&priority) {
+                    Ok(priority) => Some(priority),
+                    Err(error) => {
+                        println!(
+                            "{}, please run '{} help edit' for help",
+                            error, args.command
+                        );
+                        return Ok(());
+                    }
+                },
+                None => None,
+            };
+
+            let (due, scheduled) = match (
+                parse_date_update(args, "due"),
+                parse_date_update(args, "scheduled"),
+            ) {
+                (Ok(due), Ok(scheduled)) => (due, scheduled),
+                (Err(error), _) | (_, Err(error)) => {
+                    println!(
+                        "{}, please run '{} help edit' for help",
+                        error, args.command
+                    );
+                    return Ok(());
+                }
+            };
+
+            let (recurrence, complete_with_subtasks, estimate) = match (
+                parse_recurrence_update(args),
+                parse_complete_with_subtasks_update(args),
+                parse_estimate_update(args),
+            ) {
+                (Ok(recurrence), Ok(complete_with_subtasks), Ok(estimate)) => {
+                    (recurrence, complete_with_subtasks, estimate)
+                }
+                (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => {
+                    println!(
+                        "{}, please run '{} help edit' for help",
+                        error, args.command
+                    );
+                    return Ok(());
+                }
+            };
+
+            let update = TaskUpdate {
+                description,
+                due,
+                scheduled,
+                priority,
+                notes: args.args.get("notes").map(|notes| notes.join(" ")),
+                recurrence,
+                complete_with_subtasks,
+                estimate,
+            };
+
+            let task = match db.update_task(task_id, &update).await {
+                Ok(task) => task,
+                Err(sqlx::Error::RowNotFound) => {
+                    println!(
+                        "Task {} doesn't exist, please run '{} list' to view all of your tasks",
+                        task_id, args.command
+                    );
+                    return Ok(());
+                }
+                Err(error) => return Err(error.into()),
+            };
+
+            for tag in tags {
+                db.tag_task(task.id, &tag).await?;
+            }
+
+            print_tasks(db, vec![task.into()], format).await?;
+        }
+        "notes" => {
+            let task_ids = args.args.get("id");
+            let task_id = match parse_ids(task_ids).as_deref() {
+                Ok([task_id]) => *task_id,
+                Ok(_) => {
+                    println!(
+                        "Only one task can be specified, please run '{} help notes' for help",
+                        args.command
+                    );
+                    return Ok(());
+                }
+                Err(error) => {
+                    println!(
+                        "{}, please run '{} help notes' for help",
+                        error, args.command
+                    );
+                    return Ok(());
+                }
+            };
+
+            let task = match db.get_task(task_id).await {
+                Ok(task) => task,
+                Err(sqlx::Error::RowNotFound) => {
+                    println!(
+                        "Task {} doesn't exist, please run '{} list' to view all of your tasks",
+                        task_id, args.command
+                    );
+                    return Ok(());
+                }
+                Err(error) => return Err(error.into()),
+            };
+
+            let new_notes = if let Some(notes) = args.args.get("set") {
+                notes.join(" ")
+            } else if args.flags.contains("clear") {
+                "".to_owned()
+            } else if args.flags.contains("edit") {
+                editor::edit_text(&task.notes, &format!("task-{}-notes.md", task.id))?
+            } else if format.is_for_people() {
+                println!("{}", task.notes);
+                return Ok(());
+            } else {
+                print_tasks(db, vec![task.into()], format).await?;
+                return Ok(());
+            };
+
+            let task = db
+                .update_task(
+                    task.id,
+                    &TaskUpdate {
+                        notes: Some(new_notes),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            if format.is_for_people() {
+                println!("Updated the notes of task {}", task.id);
+            } else {
+                print_tasks(db, vec![task.into()], format).await?;
+            }
+        }
+        "move" => {
+            let task_ids = args.args.get("id");
+            let task_id = match parse_ids(task_ids).as_deref() {
+                Ok([task_id]) => *task_id,
+                Ok(_) => {
+                    println!(
+                        "Only one task can be moved at a time, please run '{} help move' for help",
+                        args.command
+                    );
+                    return Ok(());
+                }
+                Err(error) => {
+                    println!(
+                        "{}, please run '{} help move' for help",
+                        error, args.command
+                    );
+                    return Ok(());
+                }
+            };
+
+            let new_parent = match (args.args.get("parent"), args.flags.contains("top")) {
+                (Some(_), true) => {
+                    println!(
+                        "A task can't be moved to the top level and under a parent at the same time, please run '{} help move' for help",
+                        args.command
+                    );
+                    return Ok(());
+                }
+                (Some(parent), false) => match parse_ids(Some(parent)).as_deref() {
+                    Ok([parent_id]) => Some(*parent_id),
+                    Ok(_) => {
+                        println!(
+                            "Only one parent can be specified, please run '{} help move' for help",
+                            args.command
+                        );
+                        return Ok(());
+                    }
+                    Err(error) => {
+                        println!(
+                            "{}, please run '{} help move' for help",
+                            error, args.command
+                        );
+                        return Ok(());
+                    }
+                },
+                (None, true) => None,
+                (None, false) => {
+                    println!(
+                        "No new parent provided, please run '{} help move' for help",
+                        args.command
+                    );
+                    return Ok(());
+                }
+            };
+
+            match db.move_task(task_id, new_parent).await {
+                Ok(task) => print_tasks(db, vec![task.into()], format).await?,
+                Err(error @ MoveTaskError::Cycle) => println!("{}", error),
+                Err(MoveTaskError::Database(sqlx::Error::RowNotFound)) => println!(
+                    "Task {} doesn't exist, please run '{} list' to view all of your tasks",
+                    task_id, args.command
+                ),
+                Err(MoveTaskError::Database(sqlx::Error::Database(error)))
+                    if error.message() == "FOREIGN KEY constraint failed" =>
+                {
+                    println!("The task you set as a parent task doesn't exist")
+                }
+                Err(MoveTaskError::Database(error)) => return Err(error.into()),
+            }
+        }
+        "start" => {
+            let task_ids = args.args.get("id");
+            let task_id = match parse_ids(task_ids).as_deref() {
+                Ok([task_id]) => *task_id,
+                Ok(_) => {
+                    println!(
+                        "Only one task can be timed at a time, please run '{} help start' for help",
+                        args.command
+                    );
+                    return Ok(());
+                }
+                Err(error) => {
+                    println!(
+                        "{}, please run '{} help start' for help",
+                        error, args.command
+                    );
+                    return Ok(());
+                }
+            };
+
+            let already_running = db.running_timer().await?;
+            match db.start_timer(task_id).await {
+                Ok((timer, _)) if already_running.as_ref() == Some(&timer) => println!(
+                    "Task {} is already being timed, for {} so far",
+                    task_id,
+                    format_duration(timer.length(Local::now().timestamp()))
+                ),
+                Ok((_, stopped)) => {
+                    if let Some(stopped) = stopped {
+                        println!(
+                            "Stopped timing task {} after {}",
+                            stopped.task,
+                            format_duration(stopped.length(Local::now().timestamp()))
+                        );
+                    }
+                    println!("Started timing task {}", task_id);
+                }
+                Err(sqlx::Error::RowNotFound) => println!(
+                    "Task {} doesn't exist, please run '{} list' to view all of your tasks",
+                    task_id, args.command
+                ),
+                Err(error) => return Err(error.into()),
+            }
+        }
+        "stop" => match db.stop_timer().await? {
+            Some(stopped) => println!(
+                "Stopped timing task {} after {}",
+                stopped.task,
+                format_duration(stopped.length(Local::now().timestamp()))
+            ),
+            None => println!(
+                "No task is being timed, run '{} start --id <ID>' to start timing one",
                 args.command
-            )
+            ),
+        },
+        "log" => {
+            let task_ids = args.args.get("id");
+            let task_id = match parse_ids(task_ids).as_deref() {
+                Ok([task_id]) => *task_id,
+                Ok(_) => {
+                    println!(
+                        "Time can only be logged on one task at a time, please run '{} help log' for help",
+                        args.command
+                    );
+                    return Ok(());
+                }
+                Err(error) => {
+                    println!("{}, please run '{} help log' for help", error, args.command);
+                    return Ok(());
+                }
+            };
+            let time = args
+                .args
+                .get("time")
+                .map(|time| time.join(" "))
+                .unwrap_or_default();
+            let (seconds, started) =
+                match (parse_duration(&time), parse_date_argument(args, "date")) {
+                    (Ok(seconds), Ok(started)) => (seconds, started),
+                    (Err(error), _) | (_, Err(error)) => {
+                        println!("{}, please run '{} help log' for help", error, args.command);
+                        return Ok(());
+                    }
+                };
+            // Without a date, the time is taken to have just been spent
+            let started = started.unwrap_or_else(|| Local::now().timestamp() - seconds);
+
+            match db.log_time(task_id, started, seconds).await {
+                Ok(_) => println!(
+                    "Logged {} on task {} from {}",
+                    format_duration(seconds),
+                    task_id,
+                    dates::format_date(started)
+                ),
+                Err(sqlx::Error::RowNotFound) => println!(
+                    "Task {} doesn't exist, please run '{} list' to view all of your tasks",
+                    task_id, args.command
+                ),
+                Err(error) => return Err(error.into()),
+            }
+        }
+        "report" => {
+            let now = Local::now().timestamp();
+            let (from, to) = match (
+                parse_date_argument(args, "from"),
+                parse_date_argument(args, "to"),
+            ) {
+                (Ok(from), Ok(to)) => (from.unwrap_or(now - 7 * 24 * 60 * 60), to.unwrap_or(now)),
+                (Err(error), _) | (_, Err(error)) => {
+                    println!(
+                        "{}, please run '{} help report' for help",
+                        error, args.command
+                    );
+                    return Ok(());
+                }
+            };
+            let by_tag = match args.args.get("by").map(|by| by.join(" ").to_lowercase()) {
+                None => false,
+                Some(by) if by == "task" => false,
+                Some(by) if by == "tag" => true,
+                Some(by) => {
+                    println!(
+                        "Invalid grouping: {}, expected task or tag, please run '{} help report' for help",
+                        by, args.command
+                    );
+                    return Ok(());
+                }
+            };
+
+            let time_spent = db.list_time_between(from, to, now).await?;
+            if time_spent.is_empty() {
+                println!(
+                    "No time was spent on any tasks from {} to {}",
+                    dates::format_date(from),
+                    dates::format_date(to)
+                );
+                return Ok(());
+            }
+
+            // A task with several tags counts towards each of them, so the total is added up from
+            // the tasks rather than the rows
+            let rows: Vec<(String, i64)> = if by_tag {
+                let task_tags = db.list_task_tags().await?;
+                let mut tag_totals: BTreeMap<String, i64> = BTreeMap::new();
+                for (task_id, seconds) in &time_spent {
+                    match task_tags.get(task_id) {
+                        Some(tags) => {
+                            for tag in tags {
+                                *tag_totals.entry(tag.clone()).or_default() += seconds;
+                            }
+                        }
+                        None => *tag_totals.entry("(untagged)".to_owned()).or_default() += seconds,
+                    }
+                }
+                tag_totals.into_iter().collect()
+            } else {
+                let mut tasks = db.list_tasks(true).await?;
+                tasks.sort_by_key(|task| task.id);
+                tasks
+                    .into_iter()
+                    .filter_map(|task| {
+                        time_spent
+                            .get(&task.id)
+                            .map(|seconds| (format!("{} {}", task.id, task.description), *seconds))
+                    })
+                    .collect()
+            };
+            let total: i64 = time_spent.values().sum();
+
+            let name_width = rows
+                .iter()
+                .map(|(name, _)| name.chars().count())
+                .max()
+                .unwrap_or_default()
+                .max("Total".len());
+            let time_width = rows
+                .iter()
+                .map(|(_, seconds)| format_duration(*seconds).len())
+                .chain([format_duration(total).len()])
+                .max()
+                .unwrap_or_default();
+            println!(
+                "Time spent from {} to {}:",
+                dates::format_date(from),
+                dates::format_date(to)
+            );
+            for (name, seconds) in rows {
+                println!(
+                    "  {:name_width$}  {:>time_width$}",
+                    name,
+                    format_duration(seconds),
+                    name_width = name_width,
+                    time_width = time_width
+                );
+            }
+            println!(
+                "  {:name_width$}  {:>time_width$}",
+                "Total",
+                format_duration(total),
+                name_width = name_width,
+                time_width = time_width
+            );
         }
+        name => unreachable!("The {} command is described but never handled", name),
     }
     Ok(())
 }
@@ -1,10 +1,66 @@
-use crate::database::FlatTaskTreeElement;
-use tabled::Table;
+use crate::{
+    config::{self, TableStyle},
+    database::FlatTaskTreeElement,
+};
+use tabled::{style::Style, Table};
+
+/// How tasks are printed, picked with `--format` (or `--raw` for the raw format)
+///
+/// The JSON formats print each task as an object with the task's `id`, `description`, `complete`,
+/// `parent`, `due`, `scheduled`, `priority` and `notes`, along with its `level`, which is how many
+/// parents it has, and `parent_ids`, the IDs of its parents starting from the top-level task. Dates
+/// are unix timestamps, or null if they aren't set. `json` prints an array of these objects, and
+/// `jsonl` prints one object per line. Fields may be added to these objects, but won't be removed or
+/// changed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum OutputFormat {
+    Table,
+    Raw,
+    Json,
+    JsonLines,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 4] = [
+        OutputFormat::Table,
+        OutputFormat::Raw,
+        OutputFormat::Json,
+        OutputFormat::JsonLines,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputFormat::Table => "table",
+            OutputFormat::Raw => "raw",
+            OutputFormat::Json => "json",
+            OutputFormat::JsonLines => "jsonl",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(text))
+            .ok_or_else(|| {
+                format!(
+                    "Invalid format: {}, expected one of {}",
+                    text,
+                    Self::ALL.map(|format| format.name()).join(", ")
+                )
+            })
+    }
+
+    /// Whether the format is meant for people to read, in which case messages about what happened
+    /// can be printed alongside the tasks
+    pub fn is_for_people(&self) -> bool {
+        matches!(self, OutputFormat::Table | OutputFormat::Raw)
+    }
+}
 
 pub(crate) fn render_table(tasks: Vec<FlatTaskTreeElement>, raw: bool) -> String {
     let mut table = Table::new(tasks);
     let table_string = if raw {
-        table.with(tabled::style::Style::empty().vertical('\t'));
+        table.with(Style::empty().vertical('\t'));
         let headerless_table = table
             .to_string()
             .lines()
@@ -13,8 +69,78 @@ pub(crate) fn render_table(tasks: Vec<FlatTaskTreeElement>, raw: bool) -> String
             .join("\n");
         strip_ansi::strip_ansi(&headerless_table)
     } else {
-        table.with(tabled::style::Style::sharp());
+        match config::get().table_style {
+            TableStyle::Sharp => table.with(Style::sharp()),
+            TableStyle::Rounded => table.with(Style::rounded()),
+            TableStyle::Modern => table.with(Style::modern()),
+            TableStyle::Extended => table.with(Style::extended()),
+            TableStyle::Ascii => table.with(Style::ascii()),
+            TableStyle::Psql => table.with(Style::psql()),
+            TableStyle::Markdown => table.with(Style::markdown()),
+            TableStyle::Dots => table.with(Style::dots()),
+            TableStyle::ReStructuredText => table.with(Style::re_structured_text()),
+            TableStyle::Blank => table.with(Style::blank()),
+        };
         table.to_string()
     };
     table_string
 }
+
+pub(crate) fn render_tasks(
+    tasks: Vec<FlatTaskTreeElement>,
+    format: OutputFormat,
+) -> serde_json::Result<String> {
+    match format {
+        OutputFormat::Table => Ok(render_table(tasks, false)),
+        OutputFormat::Raw => Ok(render_table(tasks, true)),
+        OutputFormat::Json => serde_json::to_string_pretty(&tasks),
+        OutputFormat::JsonLines => Ok(tasks
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<serde_json::Result<Vec<_>>>()?
+            .join("\n")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Task;
+
+    #[test]
+    fn render_json_lines_test() {
+        let task = Task {
+            id: 3,
+            description: "A test task".to_owned(),
+            complete: false,
+            parent: Some(2),
+            due: Some(1672531200),
+            scheduled: None,
+            priority: 1,
+            notes: "".to_owned(),
+            deleted: None,
+            created: Some(1672444800),
+            updated: Some(1672444800),
+            completed: None,
+            recurrence: None,
+            complete_with_subtasks: None,
+            estimate: Some(3600),
+        };
+        let element = FlatTaskTreeElement {
+            level: 2,
+            last_under_parent: true,
+            parent_ids: vec![1, 2],
+            progress: Some((1, 2)),
+            remaining_estimate: Some(5400),
+            task,
+        };
+
+        // Scripts rely on this shape, so it shouldn't change
+        assert_eq!(
+            render_tasks(vec![element], OutputFormat::JsonLines).unwrap(),
+            r#"{"id":3,"description":"A test task","complete":false,"parent":2,"due":1672531200,"scheduled":null,"priority":1,"notes":"","created":1672444800,"updated":1672444800,"completed":null,"recurrence":null,"complete_with_subtasks":null,"estimate":3600,"level":2,"parent_ids":[1,2]}"#
+        );
+        assert_eq!(OutputFormat::parse("JSON"), Ok(OutputFormat::Json));
+        assert!(OutputFormat::parse("xml").is_err());
+    }
+}
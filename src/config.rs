@@ -0,0 +1,364 @@
+use eyre::{eyre, Result};
+use platform_dirs::AppDirs;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, fs, io::ErrorKind, path::PathBuf, sync::OnceLock};
+
+use crate::{dates, sorting::SortOrder};
+
+/// Settings read from a TOML config file. Everything in the file is optional and anything left out
+/// is given its default value, so a missing file is the same as an empty one
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The file this config was read from, or None if it's the default config
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    /// Where the database is kept. If this isn't set, it's kept in TeaL's data directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<PathBuf>,
+    /// The borders drawn around the tables printed by the CLI
+    pub table_style: TableStyle,
+    pub list: ListConfig,
+    pub tasks: TasksConfig,
+    pub dates: DatesConfig,
+    pub theme: Theme,
+    /// Keys to use in the TUI instead of the default ones, by the part of the TUI they're used in
+    /// and then by action. For example:
+    ///
+    /// ```toml
+    /// [keys.task_list]
+    /// select_next = ["j", "ctrl+n"]
+    /// quit = ["q", "Z Z"]
+    /// ```
+    pub keys: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+/// How tasks are listed when nothing else is asked for
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListConfig {
+    pub sort: SortOrder,
+    /// Leave out tasks that are done, along with their subtasks. `list --all` still shows them
+    pub hide_done: bool,
+}
+
+/// How tasks change along with each other
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TasksConfig {
+    /// Mark a task as done once all of its subtasks are, and as not done again when one of them
+    /// isn't. This can be changed for a single task with `edit --complete-with-subtasks`
+    pub complete_with_subtasks: bool,
+}
+
+/// How dates are shown, written as [strftime](https://docs.rs/chrono/latest/chrono/format/strftime)
+/// formats
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatesConfig {
+    /// The format for dates without a time
+    pub format: String,
+    /// The format for dates with a time
+    pub format_with_time: String,
+}
+
+impl Default for DatesConfig {
+    fn default() -> Self {
+        DatesConfig {
+            format: "%Y-%m-%d".to_owned(),
+            format_with_time: "%Y-%m-%d %H:%M".to_owned(),
+        }
+    }
+}
+
+/// The colours used by both the TUI and the CLI's tables
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Headings, the mode line and the selected task
+    pub accent: ThemeColor,
+    /// Text drawn on top of the accent colour
+    pub accent_text: ThemeColor,
+    pub done: ThemeColor,
+    pub not_done: ThemeColor,
+    pub overdue: ThemeColor,
+    pub low_priority: ThemeColor,
+    pub medium_priority: ThemeColor,
+    pub high_priority: ThemeColor,
+    pub tag: ThemeColor,
+    /// Tags starting with `@`, like `@home`
+    pub context: ThemeColor,
+    /// Less important details, like due dates
+    pub muted: ThemeColor,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        use tui::style::Color;
+
+        Theme {
+            accent: ThemeColor(Color::Cyan),
+            accent_text: ThemeColor(Color::Black),
+            done: ThemeColor(Color::Green),
+            not_done: ThemeColor(Color::Red),
+            overdue: ThemeColor(Color::Red),
+            low_priority: ThemeColor(Color::Blue),
+            medium_priority: ThemeColor(Color::Yellow),
+            high_priority: ThemeColor(Color::Red),
+            tag: ThemeColor(Color::Green),
+            context: ThemeColor(Color::Magenta),
+            muted: ThemeColor(Color::DarkGray),
+        }
+    }
+}
+
+impl Theme {
+    pub fn priority(&self, priority: i64) -> ThemeColor {
+        match priority {
+            ..=1 => self.low_priority,
+            2 => self.medium_priority,
+            _ => self.high_priority,
+        }
+    }
+}
+
+/// A colour in the theme, written as a name like `cyan` or `dark_gray`, or as `#rrggbb`
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct ThemeColor(tui::style::Color);
+
+const COLOR_NAMES: [(&str, tui::style::Color, crossterm::style::Color); 17] = {
+    use crossterm::style::Color as Terminal;
+    use tui::style::Color as Tui;
+
+    [
+        ("default", Tui::Reset, Terminal::Reset),
+        ("black", Tui::Black, Terminal::Black),
+        ("red", Tui::Red, Terminal::DarkRed),
+        ("green", Tui::Green, Terminal::DarkGreen),
+        ("yellow", Tui::Yellow, Terminal::DarkYellow),
+        ("blue", Tui::Blue, Terminal::DarkBlue),
+        ("magenta", Tui::Magenta, Terminal::DarkMagenta),
+        ("cyan", Tui::Cyan, Terminal::DarkCyan),
+        ("gray", Tui::Gray, Terminal::Grey),
+        ("dark_gray", Tui::DarkGray, Terminal::DarkGrey),
+        ("light_red", Tui::LightRed, Terminal::Red),
+        ("light_green", Tui::LightGreen, Terminal::Green),
+        ("light_yellow", Tui::LightYellow, Terminal::Yellow),
+        ("light_blue", Tui::LightBlue, Terminal::Blue),
+        ("light_magenta", Tui::LightMagenta, Terminal::Magenta),
+        ("light_cyan", Tui::LightCyan, Terminal::Cyan),
+        ("white", Tui::White, Terminal::White),
+    ]
+};
+
+impl ThemeColor {
+    /// The colour for drawing in the TUI
+    pub fn tui(self) -> tui::style::Color {
+        self.0
+    }
+
+    /// The colour for printing to the terminal, as the CLI does
+    pub fn terminal(self) -> crossterm::style::Color {
+        match self.0 {
+            tui::style::Color::Rgb(r, g, b) => crossterm::style::Color::Rgb { r, g, b },
+            color => COLOR_NAMES
+                .iter()
+                .find(|(_, tui_color, _)| *tui_color == color)
+                .map(|(_, _, terminal_color)| *terminal_color)
+                .unwrap_or(crossterm::style::Color::Reset),
+        }
+    }
+}
+
+impl TryFrom<String> for ThemeColor {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let name = text.to_lowercase().replace(['-', ' '], "_");
+        if let Some((_, color, _)) = COLOR_NAMES
+            .iter()
+            .find(|(color_name, _, _)| *color_name == name)
+        {
+            return Ok(ThemeColor(*color));
+        }
+
+        match name
+            .strip_prefix('#')
+            .map(|hex| u32::from_str_radix(hex, 16))
+        {
+            Some(Ok(rgb)) if name.len() == 7 => Ok(ThemeColor(tui::style::Color::Rgb(
+                (rgb >> 16) as u8,
+                (rgb >> 8) as u8,
+                rgb as u8,
+            ))),
+            _ => Err(format!(
+                "Invalid colour: {}, expected #rrggbb or one of {}",
+                text,
+                COLOR_NAMES.map(|(name, _, _)| name).join(", ")
+            )),
+        }
+    }
+}
+
+impl From<ThemeColor> for String {
+    fn from(color: ThemeColor) -> Self {
+        match color.0 {
+            tui::style::Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+            color => COLOR_NAMES
+                .iter()
+                .find(|(_, tui_color, _)| *tui_color == color)
+                .map(|(name, _, _)| (*name).to_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The borders drawn around tables, named after the `tabled` styles they use
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TableStyle {
+    #[default]
+    Sharp,
+    Rounded,
+    Modern,
+    Extended,
+    Ascii,
+    Psql,
+    Markdown,
+    Dots,
+    ReStructuredText,
+    Blank,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// The config TeaL was started with. This is global, as settings like the date format are needed
+/// deep inside rendering code such as `Tabled` implementations. Until [`set`] is called this is the
+/// default config
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Sets the config returned by [`get`]. This can only be done once, before anything reads it
+pub fn set(config: Config) {
+    if CONFIG.set(config).is_err() {
+        panic!("config::set was called after the config had already been used");
+    }
+}
+
+impl Config {
+    /// Where the config file is: the path given with `--config` if there was one, then the
+    /// `TEAL_CONFIG` environment variable, and otherwise config.toml in TeaL's config directory.
+    /// This is platform-dependant but generally it is ~/.config/TeaL/config.toml in Linux
+    ///
+    /// The second value is whether the path was picked by the user rather than being the default
+    pub fn path(argument: Option<&str>) -> (PathBuf, bool) {
+        match argument
+            .map(str::to_owned)
+            .or_else(|| env::var("TEAL_CONFIG").ok().filter(|path| !path.is_empty()))
+        {
+            Some(path) => (PathBuf::from(path), true),
+            None => (
+                AppDirs::new(Some("TeaL"), true)
+                    .unwrap()
+                    .config_dir
+                    .join("config.toml"),
+                false,
+            ),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(text).map_err(|error| error.to_string())?;
+        dates::check_format(&config.dates.format)?;
+        dates::check_format(&config.dates.format_with_time)?;
+
+        Ok(config)
+    }
+
+    /// Reads the config from the file given with `--config`, or wherever [`Config::path`] says it
+    /// is. It's fine for the default config file not to exist, but one the user picked has to
+    pub fn load(argument: Option<&str>) -> Result<Self> {
+        let (path, picked_by_user) = Self::path(argument);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == ErrorKind::NotFound && !picked_by_user => {
+                return Ok(Self::default())
+            }
+            Err(error) => return Err(eyre!("Couldn't read {}: {}", path.display(), error)),
+        };
+
+        let mut config = Self::parse(&text)
+            .map_err(|error| eyre!("Couldn't read {}: {}", path.display(), error))?;
+        config.source = Some(path);
+        Ok(config)
+    }
+
+    /// The config as it would be written in a config file, with every setting filled in
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config_test() {
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+
+        let config = Config::parse(
+            r##"
+            database = "/tmp/tasks.db"
+            table_style = "rounded"
+
+            [list]
+            sort = "priority"
+
+            [tasks]
+            complete_with_subtasks = true
+
+            [theme]
+            accent = "light-blue"
+            tag = "#ff8000"
+
+            [keys.task_list]
+            select_next = ["j", "ctrl+n"]
+            "##,
+        )
+        .unwrap();
+        assert_eq!(config.database, Some(PathBuf::from("/tmp/tasks.db")));
+        assert_eq!(config.table_style, TableStyle::Rounded);
+        assert_eq!(config.list.sort, SortOrder::Priority);
+        assert!(!config.list.hide_done);
+        assert!(config.tasks.complete_with_subtasks);
+        assert_eq!(config.dates, DatesConfig::default());
+        assert_eq!(config.theme.accent.tui(), tui::style::Color::LightBlue);
+        assert_eq!(
+            config.theme.tag.terminal(),
+            crossterm::style::Color::Rgb {
+                r: 255,
+                g: 128,
+                b: 0
+            }
+        );
+        assert_eq!(
+            config.keys["task_list"]["select_next"],
+            vec!["j".to_owned(), "ctrl+n".to_owned()]
+        );
+
+        assert!(Config::parse("colour = true").is_err());
+        assert!(Config::parse("[theme]\naccent = \"sky\"").is_err());
+        assert!(Config::parse("[dates]\nformat = \"%Q\"").is_err());
+    }
+
+    #[test]
+    fn print_config_test() {
+        let config = Config::parse("[theme]\ntag = \"#ff8000\"").unwrap();
+
+        // Printing the config and reading it back in shouldn't change anything
+        assert_eq!(Config::parse(&config.to_toml().unwrap()).unwrap(), config);
+    }
+}
@@ -1,30 +1,204 @@
 use platform_dirs::AppDirs;
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::{self, File},
     path::PathBuf,
 };
 
+use chrono::{DateTime, Local};
 use color_eyre::Report;
 use crossterm::style::Stylize;
+use serde::Serialize;
 use tabled::Tabled;
 
-use sqlx::{sqlite::SqliteQueryResult, Connection, SqliteConnection};
+use sqlx::{Connection, SqliteConnection};
+
+use crate::{config, dates, durations::format_duration, migrations, recurrence::Recurrence};
 
 // Schema is on <app.dbdesigner.net>
 
 pub struct Database {
     connection: SqliteConnection,
+    /// How many changes are being made, see [`Database::begin_change`]
+    change_depth: u32,
 }
 
-#[derive(Debug, Clone)]
+/// How many entries are kept in the journal for undoing. Older ones are forgotten
+const JOURNAL_LENGTH: i64 = 100;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Task {
     pub id: i64, // IMPORTANT: This begins from 1, *not* 0
     pub description: String,
     pub complete: bool,
     pub parent: Option<i64>,
+    pub due: Option<i64>,
+    pub scheduled: Option<i64>,
+    pub priority: i64,
+    pub notes: String,
+    /// When the task was moved to the trash, or None if it hasn't been
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<i64>,
+    /// When the task was created. This is None for tasks made before TeaL kept track of it, as are
+    /// the other timestamps
+    pub created: Option<i64>,
+    /// When any of the task's fields last changed
+    pub updated: Option<i64>,
+    /// When the task was marked as done, or None if it isn't done
+    pub completed: Option<i64>,
+    /// How often the task repeats, like `every 2 weeks`, or None if it doesn't (see
+    /// [`Recurrence`])
+    pub recurrence: Option<String>,
+    /// Whether the task is marked as done once all of its subtasks are, and as not done again when
+    /// one of them isn't. None means the task follows the `complete_with_subtasks` setting in the
+    /// config
+    pub complete_with_subtasks: Option<bool>,
+    /// How many seconds the task is expected to take, not counting its subtasks, or None if it
+    /// hasn't been estimated
+    pub estimate: Option<i64>,
+}
+
+/// The names of each priority a task can have, indexed by the priority's number
+pub const PRIORITY_NAMES: [&str; 4] = ["None", "Low", "Medium", "High"];
+
+/// Parses a priority either by name (case-insensitively) or by number
+pub fn parse_priority(text: &str) -> Result<i64, String> {
+    if let Some(priority) = PRIORITY_NAMES
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(text))
+    {
+        return Ok(priority as i64);
+    }
+
+    match text.parse::<i64>() {
+        Ok(priority) if (0..PRIORITY_NAMES.len() as i64).contains(&priority) => Ok(priority),
+        _ => Err(format!(
+            "Invalid priority: {}, expected one of {}",
+            text,
+            PRIORITY_NAMES.join(", ").to_lowercase()
+        )),
+    }
+}
+
+/// Everything needed to create a task. Use [`NewTask::new`] and then fill in whichever optional
+/// fields you need
+#[derive(Debug, Clone, Default)]
+pub struct NewTask {
+    pub description: String,
+    pub parent: Option<i64>,
+    pub due: Option<i64>,
+    pub scheduled: Option<i64>,
+    pub priority: i64,
+    pub notes: String,
+    /// Tags and contexts to add to the task, including their sigils (see [`crate::tags`])
+    pub tags: Vec<String>,
+    pub recurrence: Option<Recurrence>,
+    pub complete_with_subtasks: Option<bool>,
+    /// In seconds
+    pub estimate: Option<i64>,
+}
+
+impl NewTask {
+    pub fn new(description: &str, parent: Option<i64>) -> Self {
+        Self {
+            description: description.to_owned(),
+            parent,
+            ..Default::default()
+        }
+    }
+}
+/// Changes to make to a task with [`Database::update_task`]. Fields which are `None` are left as
+/// they are, so an update made with `TaskUpdate::default()` does nothing
+#[derive(Debug, Clone, Default)]
+pub struct TaskUpdate {
+    pub description: Option<String>,
+    /// `Some(None)` removes the due date
+    pub due: Option<Option<i64>>,
+    /// `Some(None)` removes the scheduled date
+    pub scheduled: Option<Option<i64>>,
+    pub priority: Option<i64>,
+    pub notes: Option<String>,
+    /// `Some(None)` stops the task from repeating
+    pub recurrence: Option<Option<Recurrence>>,
+    /// `Some(None)` makes the task follow the config again
+    pub complete_with_subtasks: Option<Option<bool>>,
+    /// In seconds. `Some(None)` removes the estimate
+    pub estimate: Option<Option<i64>>,
+}
+
+/// A stretch of time spent on a task, see [`Database::start_timer`] and [`Database::log_time`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimeEntry {
+    pub id: i64,
+    pub task: i64,
+    pub started: i64,
+    /// When the time stopped being recorded, or None if the timer is still running
+    pub stopped: Option<i64>,
+}
+
+impl TimeEntry {
+    /// How many seconds the entry lasts, counting a running timer up until `now`
+    pub fn length(&self, now: i64) -> i64 {
+        self.stopped.unwrap_or(now) - self.started
+    }
+}
+
+#[derive(Debug)]
+pub enum MoveTaskError {
+    /// The new parent is the task itself or one of its subtasks, so moving it there would make a
+    /// cycle
+    Cycle,
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for MoveTaskError {
+    fn from(error: sqlx::Error) -> Self {
+        MoveTaskError::Database(error)
+    }
+}
+
+impl std::fmt::Display for MoveTaskError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveTaskError::Cycle => write!(
+                formatter,
+                "A task can't be moved under itself or one of its subtasks"
+            ),
+            MoveTaskError::Database(error) => error.fmt(formatter),
+        }
+    }
+}
+
+impl std::error::Error for MoveTaskError {}
+
+#[derive(Debug)]
+pub enum BlockTaskError {
+    /// The blocking task is the task itself or is already blocked by it, so neither could ever be
+    /// done
+    Cycle,
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for BlockTaskError {
+    fn from(error: sqlx::Error) -> Self {
+        BlockTaskError::Database(error)
+    }
+}
+
+impl std::fmt::Display for BlockTaskError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockTaskError::Cycle => write!(
+                formatter,
+                "A task can't be blocked by itself or a task it's blocking"
+            ),
+            BlockTaskError::Database(error) => error.fmt(formatter),
+        }
+    }
 }
+
+impl std::error::Error for BlockTaskError {}
 // See also: https://www.geeksforgeeks.org/recursive-join-in-sql/
 
 impl From<Task> for i64 {
@@ -34,16 +208,27 @@ impl From<Task> for i64 {
 }
 
 impl Tabled for FlatTaskTreeElement {
-    const LENGTH: usize = 3;
+    const LENGTH: usize = 8;
 
     fn headers() -> Vec<Cow<'static, str>> {
-        vec![
-            "Number".cyan().bold().to_string().into(),
-            "Task".cyan().bold().to_string().into(),
-            "Done?".cyan().bold().to_string().into(),
+        let accent = config::get().theme.accent.terminal();
+
+        [
+            "Number",
+            "Task",
+            "Done?",
+            "Progress",
+            "Time left",
+            "Priority",
+            "Due",
+            "Scheduled",
         ]
+        .map(|header| header.with(accent).bold().to_string().into())
+        .to_vec()
     }
-    fn fields(&self) -> Vec<Cow<str>> {
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        let theme = &config::get().theme;
+
         vec![
             (self
                 .parent_ids
@@ -56,10 +241,50 @@ impl Tabled for FlatTaskTreeElement {
                 .into(),
             self.task.description.to_owned().into(),
             if self.task.complete {
-                "Done".green().to_string().into()
+                "Done".with(theme.done.terminal()).to_string().into()
             } else {
-                "Not done".red().to_string().into()
+                "Not done"
+                    .with(theme.not_done.terminal())
+                    .to_string()
+                    .into()
+            },
+            self.progress
+                .map(|(done, total)| format!("{}/{}", done, total))
+                .unwrap_or_default()
+                .into(),
+            self.remaining_estimate
+                .filter(|seconds| *seconds > 0)
+                .map(format_duration)
+                .unwrap_or_default()
+                .into(),
+            match self.task.priority {
+                0 => "".into(),
+                1 | 2 => PRIORITY_NAMES[self.task.priority as usize]
+                    .with(theme.priority(self.task.priority).terminal())
+                    .to_string()
+                    .into(),
+                _ => PRIORITY_NAMES[3]
+                    .with(theme.priority(3).terminal())
+                    .bold()
+                    .to_string()
+                    .into(),
             },
+            match self.task.due {
+                Some(due) if !self.task.complete && dates::is_overdue(due, Local::now()) => {
+                    dates::format_date(due)
+                        .with(theme.overdue.terminal())
+                        .bold()
+                        .to_string()
+                        .into()
+                }
+                Some(due) => dates::format_date(due).into(),
+                None => "".into(),
+            },
+            self.task
+                .scheduled
+                .map(dates::format_date)
+                .unwrap_or_default()
+                .into(),
         ]
     }
 }
@@ -70,7 +295,18 @@ impl From<&TaskTree> for Task {
             id: item.id,
             description: item.description.clone(),
             complete: item.complete,
-            parent: None,
+            parent: item.parent,
+            due: item.due,
+            scheduled: item.scheduled,
+            priority: item.priority,
+            notes: item.notes.clone(),
+            deleted: item.deleted,
+            created: item.created,
+            updated: item.updated,
+            completed: item.completed,
+            recurrence: item.recurrence.clone(),
+            complete_with_subtasks: item.complete_with_subtasks,
+            estimate: item.estimate,
         }
     }
 }
@@ -80,10 +316,39 @@ pub struct TaskTree {
     pub id: i64,
     pub description: String,
     pub complete: bool,
+    pub parent: Option<i64>,
+    pub due: Option<i64>,
+    pub scheduled: Option<i64>,
+    pub priority: i64,
+    pub notes: String,
+    pub deleted: Option<i64>,
+    pub created: Option<i64>,
+    pub updated: Option<i64>,
+    pub completed: Option<i64>,
+    pub recurrence: Option<String>,
+    pub complete_with_subtasks: Option<bool>,
+    pub estimate: Option<i64>,
     pub children: Vec<TaskTree>,
     pub level: usize,
 }
 
+impl TaskTree {
+    /// Adds up the estimates of the task and all of its subtasks which aren't done yet, in seconds.
+    /// Tasks without an estimate count as nothing, so this is 0 if none of them have one
+    pub fn remaining_estimate(&self) -> i64 {
+        let own = if self.complete {
+            0
+        } else {
+            self.estimate.unwrap_or_default()
+        };
+        own + self
+            .children
+            .iter()
+            .map(TaskTree::remaining_estimate)
+            .sum::<i64>()
+    }
+}
+
 struct TaskAndChildMap<'a, 'b> {
     task: &'a Task,
     child_map: &'b HashMap<i64, Vec<Task>>,
@@ -124,6 +389,18 @@ impl<'a> From<TaskAndChildTrees<'a>> for TaskTree {
             id: task_and_tree.task.id,
             description: task_and_tree.task.description.clone(),
             complete: task_and_tree.task.complete,
+            parent: task_and_tree.task.parent,
+            due: task_and_tree.task.due,
+            scheduled: task_and_tree.task.scheduled,
+            priority: task_and_tree.task.priority,
+            notes: task_and_tree.task.notes.clone(),
+            deleted: task_and_tree.task.deleted,
+            created: task_and_tree.task.created,
+            updated: task_and_tree.task.updated,
+            completed: task_and_tree.task.completed,
+            recurrence: task_and_tree.task.recurrence.clone(),
+            complete_with_subtasks: task_and_tree.task.complete_with_subtasks,
+            estimate: task_and_tree.task.estimate,
             children: task_and_tree.children,
             level: task_and_tree.level,
         }
@@ -150,7 +427,7 @@ impl TryFrom<Vec<Task>> for TaskTree {
                     root = Some(task);
                 }
                 Some(parent) => {
-                    children.entry(parent).or_insert(Vec::default()).push(task);
+                    children.entry(parent).or_default().push(task);
                 }
             }
         }
@@ -190,11 +467,37 @@ impl IntoIterator for TaskTree {
     }
 }
 
+/// A task from a flattened task tree. This is also what `--format json` prints, as the task's own
+/// fields followed by its `level` and `parent_ids`
+#[derive(Serialize)]
 pub struct FlatTaskTreeElement {
+    #[serde(flatten)]
+    pub task: Task,
     pub level: usize,
+    #[serde(skip)]
     pub last_under_parent: bool,
-    pub task: Task,
     pub parent_ids: Vec<i64>,
+    /// How many of the task's subtasks are done and how many subtasks it has, or None if it has none
+    /// or this hasn't been filled in (see [`Database::list_progress`])
+    #[serde(skip)]
+    pub progress: Option<(usize, usize)>,
+    /// How many seconds of work are estimated to be left on the task and its subtasks, or None if
+    /// this hasn't been filled in (see [`TaskTree::remaining_estimate`])
+    #[serde(skip)]
+    pub remaining_estimate: Option<i64>,
+}
+
+impl From<Task> for FlatTaskTreeElement {
+    fn from(task: Task) -> Self {
+        FlatTaskTreeElement {
+            level: 0,
+            last_under_parent: false,
+            parent_ids: task.parent.into_iter().collect(),
+            progress: None,
+            remaining_estimate: None,
+            task,
+        }
+    }
 }
 
 struct TaskTreeElement {
@@ -237,6 +540,8 @@ impl From<TaskTreeElement> for Vec<FlatTaskTreeElement> {
             task: Task::from(&tree.task_tree),
             last_under_parent: tree.last_under_parent,
             parent_ids: tree.parent_ids,
+            progress: None,
+            remaining_estimate: Some(tree.task_tree.remaining_estimate()),
         }];
 
         let children_length = tree.task_tree.children.len();
@@ -255,28 +560,259 @@ impl From<TaskTreeElement> for Vec<FlatTaskTreeElement> {
     }
 }
 
+async fn insert_task_tag(
+    connection: &mut SqliteConnection,
+    task_id: i64,
+    tag: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO tags (name) VALUES (?) ON CONFLICT DO NOTHING",
+        tag
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO task_tags (task, tag)
+        SELECT ?, id FROM tags WHERE name = ?
+        ON CONFLICT DO NOTHING",
+        task_id,
+        tag
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(())
+}
+
+/// Which row a journal change was made to
+#[derive(PartialEq, Eq)]
+enum JournalKey {
+    Task(i64),
+    Tag(i64),
+    TaskTag(i64, i64),
+    TaskDependency(i64, i64),
+    TimeEntry(i64),
+}
+
+/// How a row changed by a journal entry needs to end up, with None meaning it shouldn't exist
+struct JournalRow {
+    key: JournalKey,
+    target: Option<String>,
+}
+
+impl JournalRow {
+    fn key(table_name: &str, row: Option<&str>) -> Option<JournalKey> {
+        let row: serde_json::Value = serde_json::from_str(row?).ok()?;
+        let column = |name: &str| row.get(name).and_then(serde_json::Value::as_i64);
+
+        match table_name {
+            "tasks" => Some(JournalKey::Task(column("id")?)),
+            "tags" => Some(JournalKey::Tag(column("id")?)),
+            "task_tags" => Some(JournalKey::TaskTag(column("task")?, column("tag")?)),
+            "task_dependencies" => Some(JournalKey::TaskDependency(
+                column("task")?,
+                column("blocked_by")?,
+            )),
+            "time_entries" => Some(JournalKey::TimeEntry(column("id")?)),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a time entry recorded in the journal is a timer that hasn't been stopped
+fn is_running_timer(entry: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(entry)
+        .map(|entry| {
+            entry
+                .get("stopped")
+                .map_or(true, serde_json::Value::is_null)
+        })
+        .unwrap_or(false)
+}
+
+async fn restore_time_entry(
+    connection: &mut SqliteConnection,
+    entry: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO time_entries (id, task, started, stopped)
+        VALUES (json_extract(?1, '$.id'),
+                json_extract(?1, '$.task'),
+                json_extract(?1, '$.started'),
+                json_extract(?1, '$.stopped'))
+        ON CONFLICT (id) DO UPDATE SET task = excluded.task,
+                                       started = excluded.started,
+                                       stopped = excluded.stopped",
+        entry
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(())
+}
+
+/// Puts rows recorded in the journal back the way they were. Any columns added to the journaled
+/// tables need to be restored here as well as being added to the journal's triggers
+async fn restore_rows(
+    connection: &mut SqliteConnection,
+    rows: &[JournalRow],
+) -> Result<(), sqlx::Error> {
+    // Tasks are put back without their parents first, as setting a parent before the task's
+    // final subtasks have been moved out from under it could look like a cycle
+    for row in rows {
+        match (&row.key, &row.target) {
+            (JournalKey::Task(_), Some(task)) => {
+                sqlx::query!(
+                    "INSERT INTO tasks (id, description, complete, parent, due, scheduled, priority, notes, deleted, created, updated, completed, recurrence, complete_with_subtasks, estimate)
+                    VALUES (json_extract(?1, '$.id'),
+                            json_extract(?1, '$.description'),
+                            json_extract(?1, '$.complete'),
+                            NULL,
+                            json_extract(?1, '$.due'),
+                            json_extract(?1, '$.scheduled'),
+                            json_extract(?1, '$.priority'),
+                            json_extract(?1, '$.notes'),
+                            json_extract(?1, '$.deleted'),
+                            json_extract(?1, '$.created'),
+                            json_extract(?1, '$.updated'),
+                            json_extract(?1, '$.completed'),
+                            json_extract(?1, '$.recurrence'),
+                            json_extract(?1, '$.complete_with_subtasks'),
+                            json_extract(?1, '$.estimate'))
+                    ON CONFLICT (id) DO UPDATE SET description = excluded.description,
+                                                   complete = excluded.complete,
+                                                   parent = NULL,
+                                                   due = excluded.due,
+                                                   scheduled = excluded.scheduled,
+                                                   priority = excluded.priority,
+                                                   notes = excluded.notes,
+                                                   deleted = excluded.deleted,
+                                                   created = excluded.created,
+                                                   updated = excluded.updated,
+                                                   completed = excluded.completed,
+                                                   recurrence = excluded.recurrence,
+                                                   complete_with_subtasks = excluded.complete_with_subtasks,
+                                                   estimate = excluded.estimate",
+                    task
+                )
+                .execute(&mut *connection)
+                .await?;
+            }
+            (JournalKey::Tag(_), Some(tag)) => {
+                sqlx::query!(
+                    "INSERT INTO tags (id, name) VALUES (json_extract(?1, '$.id'), json_extract(?1, '$.name'))
+                    ON CONFLICT (id) DO UPDATE SET name = excluded.name",
+                    tag
+                )
+                .execute(&mut *connection)
+                .await?;
+            }
+            // Only one timer can run at a time, so timers are stopped or removed before any are
+            // started again
+            (JournalKey::TimeEntry(id), None) => {
+                sqlx::query!("DELETE FROM time_entries WHERE id = ?", id)
+                    .execute(&mut *connection)
+                    .await?;
+            }
+            (JournalKey::TimeEntry(_), Some(entry)) if !is_running_timer(entry) => {
+                restore_time_entry(&mut *connection, entry).await?;
+            }
+            _ => {}
+        }
+    }
+
+    for row in rows {
+        match (&row.key, &row.target) {
+            (JournalKey::Task(id), Some(task)) => {
+                sqlx::query!(
+                    "UPDATE tasks SET parent = json_extract(?, '$.parent') WHERE id = ?",
+                    task,
+                    id
+                )
+                .execute(&mut *connection)
+                .await?;
+            }
+            (JournalKey::TaskTag(task, tag), Some(_)) => {
+                sqlx::query!(
+                    "INSERT OR IGNORE INTO task_tags (task, tag) VALUES (?, ?)",
+                    task,
+                    tag
+                )
+                .execute(&mut *connection)
+                .await?;
+            }
+            (JournalKey::TaskTag(task, tag), None) => {
+                sqlx::query!(
+                    "DELETE FROM task_tags WHERE task = ? AND tag = ?",
+                    task,
+                    tag
+                )
+                .execute(&mut *connection)
+                .await?;
+            }
+            (JournalKey::TaskDependency(task, blocked_by), Some(_)) => {
+                sqlx::query!(
+                    "INSERT OR IGNORE INTO task_dependencies (task, blocked_by) VALUES (?, ?)",
+                    task,
+                    blocked_by
+                )
+                .execute(&mut *connection)
+                .await?;
+            }
+            (JournalKey::TaskDependency(task, blocked_by), None) => {
+                sqlx::query!(
+                    "DELETE FROM task_dependencies WHERE task = ? AND blocked_by = ?",
+                    task,
+                    blocked_by
+                )
+                .execute(&mut *connection)
+                .await?;
+            }
+            (JournalKey::TimeEntry(_), Some(entry)) if is_running_timer(entry) => {
+                restore_time_entry(&mut *connection, entry).await?;
+            }
+            _ => {}
+        }
+    }
+
+    for row in rows {
+        match (&row.key, &row.target) {
+            (JournalKey::Task(id), None) => {
+                sqlx::query!("DELETE FROM tasks WHERE id = ?", id)
+                    .execute(&mut *connection)
+                    .await?;
+            }
+            (JournalKey::Tag(id), None) => {
+                sqlx::query!("DELETE FROM tags WHERE id = ?", id)
+                    .execute(&mut *connection)
+                    .await?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
 impl Database {
     /// Create a database object with a connection to an SQLite database.
     /// The database file will be created if it doesn't exist, along with any parent directories
     ///
     /// # Arguments
     /// * `path` - The path to the SQLite database file  
-    ///            If the file doesn't exist, it will be created along with any missing parent directories  
-    ///            If you want to open an in-memory database, you can pass `"sqlite::memory:"` as the path
-    ///            If you pass None, a database will be created in the application's data directory. This is platform-dependant but generally it is ~/.local/share/TeaL/TeaL.db in Linux
+    ///   If the file doesn't exist, it will be created along with any missing parent directories  
+    ///   If you want to open an in-memory database, you can pass `"sqlite::memory:"` as the path
+    ///   If you pass None, a database will be created in the application's data directory. This is platform-dependant but generally it is ~/.local/share/TeaL/TeaL.db in Linux
     ///
     ///
     pub async fn new(path: Option<String>) -> Result<Self, sqlx::Error> {
         let path_str = if path == Some("sqlite::memory:".to_owned()) {
             "sqlite::memory:".to_owned()
         } else {
-            let path = if let Some(path) = path {
-                PathBuf::from(path)
-            } else {
-                AppDirs::new(Some("TeaL"), true)
-                    .unwrap()
-                    .data_dir
-                    .join("TeaL.db")
+            let path = match path {
+                Some(path) => PathBuf::from(path),
+                None => Self::default_path(),
             };
 
             if !path.try_exists()? {
@@ -292,190 +828,2283 @@ impl Database {
 
         Ok(Self {
             connection: SqliteConnection::connect(&path_str).await?,
+            change_depth: 0,
         })
     }
 
-    pub async fn setup(&mut self) -> Result<SqliteQueryResult, sqlx::Error> {
-        sqlx::query(include_str!("./create.sql"))
+    /// Where the database is kept if no other path is given. This is platform-dependant but generally
+    /// it is ~/.local/share/TeaL/TeaL.db in Linux
+    pub fn default_path() -> PathBuf {
+        AppDirs::new(Some("TeaL"), true)
+            .unwrap()
+            .data_dir
+            .join("TeaL.db")
+    }
+
+    /// Brings the database schema up to date, applying any migrations that haven't been run yet
+    ///
+    /// This will refuse to touch a database which was created by a newer version of TeaL
+    pub async fn setup(&mut self) -> eyre::Result<()> {
+        migrations::migrate_to(&mut self.connection, migrations::LATEST_VERSION).await?;
+
+        // If TeaL stopped in the middle of a change, the entry for it would still be recording
+        sqlx::query!("UPDATE journal SET recording = false WHERE recording")
             .execute(&mut self.connection)
-            .await
+            .await?;
+        Ok(())
     }
 
-    pub async fn add_task(&mut self, task: &str, parent: Option<i64>) -> Result<Task, sqlx::Error> {
-        sqlx::query_as!(
-            Task,
-            "INSERT INTO tasks VALUES (null, ?, false, ?) 
-            RETURNING id,
-                      description as 'description!',
-                      complete, 
-                      parent",
-            task,
-            parent,
-        )
-        .fetch_one(&mut self.connection)
-        .await
+    pub async fn close(self) -> Result<(), sqlx::Error> {
+        self.connection.close().await
     }
 
-    /// Removes a task from the database by ID, and returns how many rows were affected
-    /// This will be 0 if the task was not found
-    /// This will be 1 if the task was found and removed
-    /// If the task had children, they may be cascade deleted which will be reflected in the return value
-    pub async fn remove_task(&mut self, task_id: i64) -> Result<Vec<Task>, sqlx::Error> {
-        sqlx::query_as!(
-            Task,
-            "WITH RECURSIVE subtask_tree AS (
-                SELECT *
-                FROM tasks
-                WHERE id = ?
-            UNION ALL
-                SELECT subtasks.*
-                FROM tasks subtasks
-            INNER JOIN subtask_tree ON subtask_tree.id = subtasks.parent
-        )
-        DELETE FROM tasks WHERE id IN (SELECT id FROM subtask_tree)
-            RETURNING id as 'id!',
-                      description as 'description!',
-                      complete as 'complete!', 
-                      parent",
-            task_id
-        )
-        .fetch_all(&mut self.connection)
-        .await
-        // Special thanks to https://stackoverflow.com/a/10381384/12293760 for supplying a way to
-        // delete rather than just select the tasks from the recursive subtree
-        //
-        // Note that we can't just ON DELETE CASCADE as that doesn't let us return the deleted
-        // tasks. This is the only way I found to do both in a single query.
+    /// Starts recording a change in the journal, so that it can be undone. Everything done to the
+    /// tasks until the matching [`Database::end_change`] is undone together as one change
+    ///
+    /// Each method that changes tasks records itself, but this can be called around several of them
+    /// to group them into one change, as only the outermost call starts a new entry
+    pub async fn begin_change(&mut self, description: &str) -> Result<(), sqlx::Error> {
+        if self.change_depth == 0 {
+            sqlx::query!("INSERT INTO journal (description) VALUES (?)", description)
+                .execute(&mut self.connection)
+                .await?;
+        }
+        self.change_depth += 1;
+
+        Ok(())
     }
 
-    pub async fn set_completion(
-        &mut self,
-        index: i64,
-        completed: bool,
-    ) -> Result<Task, sqlx::Error> {
-        let task = sqlx::query!(
-            "UPDATE tasks SET complete = ? WHERE id = ?
-                        RETURNING *",
-            completed,
-            index
+    /// Finishes recording a change started by [`Database::begin_change`]. A change which didn't
+    /// end up changing anything is forgotten, otherwise anything that was undone can no longer be
+    /// redone
+    pub async fn end_change(&mut self) -> Result<(), sqlx::Error> {
+        self.change_depth = self.change_depth.saturating_sub(1);
+        if self.change_depth > 0 {
+            return Ok(());
+        }
+
+        let changed = sqlx::query_scalar!(
+            "SELECT COUNT(1) FROM journal_changes
+            INNER JOIN journal ON journal.id = journal_changes.entry
+            WHERE journal.recording"
         )
         .fetch_one(&mut self.connection)
-        .await?;
-
-        match (task.id, task.description, task.complete, task.parent) {
-            (Some(id), Some(description), Some(complete), parent) => Ok(Task {
-                id,
-                description,
-                complete,
-                parent,
-            }),
-            _ => Err(sqlx::Error::RowNotFound),
+        .await?
+            > 0;
+
+        if changed {
+            sqlx::query!("DELETE FROM journal WHERE undone")
+                .execute(&mut self.connection)
+                .await?;
+            sqlx::query!("UPDATE journal SET recording = false WHERE recording")
+                .execute(&mut self.connection)
+                .await?;
+            sqlx::query!(
+                "DELETE FROM journal WHERE id NOT IN (SELECT id FROM journal ORDER BY id DESC LIMIT ?)",
+                JOURNAL_LENGTH
+            )
+            .execute(&mut self.connection)
+            .await?;
+        } else {
+            sqlx::query!("DELETE FROM journal WHERE recording")
+                .execute(&mut self.connection)
+                .await?;
         }
+
+        Ok(())
     }
 
-    pub async fn list_tasks(&mut self, include_children: bool) -> Result<Vec<Task>, sqlx::Error> {
-        if include_children {
-            sqlx::query_as!(Task, "SELECT * FROM tasks")
-                .fetch_all(&mut self.connection)
-                .await
-        } else {
-            sqlx::query_as!(Task, "SELECT * FROM tasks WHERE parent IS NULL")
-                // == null is invalid (https://www.sqlitetutorial.net/sqlite-is-null/)
-                .fetch_all(&mut self.connection)
-                .await
+    /// Undoes the latest change that hasn't been undone yet, or redoes the earliest change that was
+    /// undone, returning its description. This is None if there's nothing to undo or redo
+    pub async fn undo_or_redo(&mut self, undoing: bool) -> Result<Option<String>, sqlx::Error> {
+        let redoing = !undoing;
+        let entry = sqlx::query!(
+            "SELECT id as 'id!', description as 'description!' FROM journal
+            WHERE NOT recording AND undone = ?1
+            ORDER BY CASE WHEN ?1 THEN -id ELSE id END DESC
+            LIMIT 1",
+            redoing
+        )
+        .fetch_optional(&mut self.connection)
+        .await?;
+
+        match entry {
+            Some(entry) => {
+                self.replay(entry.id, undoing).await?;
+                Ok(Some(entry.description))
+            }
+            None => Ok(None),
         }
     }
 
-    pub async fn list_subtasks(&mut self, task_id: i64) -> eyre::Result<TaskTree> {
-        let tasks = sqlx::query_as!(
-            Task,
-            "WITH RECURSIVE subtask_tree AS (
-                SELECT *
-                FROM tasks
-                WHERE id = ?
-            UNION ALL
-                SELECT subtasks.*
-                FROM tasks subtasks
-            INNER JOIN subtask_tree ON subtask_tree.id = subtasks.parent
-        )
-        SELECT * FROM subtask_tree",
-            task_id
+    /// Puts every row changed by a journal entry back to how it was before the entry if undoing, or
+    /// after it if redoing, all in one transaction
+    async fn replay(&mut self, entry: i64, undoing: bool) -> Result<(), sqlx::Error> {
+        let mut changes = sqlx::query!(
+            "SELECT table_name, before, after FROM journal_changes WHERE entry = ? ORDER BY id",
+            entry
         )
         .fetch_all(&mut self.connection)
         .await?;
-        tasks.try_into()
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[tokio::test]
-    async fn add_task_test() {
-        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
-            .await
-            .unwrap();
 
-        db.setup().await.unwrap();
+        // A row can be changed several times by one entry, so undoing puts it back to how it was
+        // before the first change and redoing to how it was after the last one
+        if !undoing {
+            changes.reverse();
+        }
+        let mut rows: Vec<JournalRow> = vec![];
+        for change in changes {
+            let Some(key) = JournalRow::key(
+                &change.table_name,
+                change.before.as_deref().or(change.after.as_deref()),
+            ) else {
+                continue;
+            };
 
-        assert_eq!(db.list_tasks(true).await.unwrap().len(), 0);
+            if !rows.iter().any(|row| row.key == key) {
+                let target = if undoing { change.before } else { change.after };
+                rows.push(JournalRow { key, target });
+            }
+        }
 
-        db.add_task("A test task", None).await.unwrap();
+        let mut transaction = self.connection.begin().await?;
+        // The rows are restored one at a time, so a task can briefly point at a parent that hasn't
+        // been put back yet
+        sqlx::query("PRAGMA defer_foreign_keys = ON")
+            .execute(&mut transaction)
+            .await?;
+        restore_rows(&mut transaction, &rows).await?;
+
+        sqlx::query!("UPDATE journal SET undone = ? WHERE id = ?", undoing, entry)
+            .execute(&mut transaction)
+            .await?;
+        transaction.commit().await
+    }
 
-        assert_eq!(db.list_tasks(true).await.unwrap().len(), 1);
+    #[cfg(test)]
+    pub(crate) fn connection(&mut self) -> &mut SqliteConnection {
+        &mut self.connection
     }
 
-    #[tokio::test]
-    async fn remove_task_test() {
-        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
-            .await
-            .unwrap();
+    pub async fn add_task(&mut self, task: &NewTask) -> Result<Task, sqlx::Error> {
+        self.begin_change("add task").await?;
+        let now = Local::now().timestamp();
+        let recurrence = task.recurrence.as_ref().map(Recurrence::to_string);
+        let result: Result<Task, sqlx::Error> = async {
+            let mut transaction = self.connection.begin().await?;
+
+            let new_task = sqlx::query_as!(
+                Task,
+                "INSERT INTO tasks (description, complete, parent, due, scheduled, priority, notes, created, updated, recurrence, complete_with_subtasks, estimate)
+            VALUES (?1, false, ?2, ?3, ?4, ?5, ?6, ?7, ?7, ?8, ?9, ?10)
+            RETURNING id as 'id!',
+                      description as 'description!',
+                      complete as 'complete!',
+                      parent,
+                      due,
+                      scheduled,
+                      priority as 'priority!',
+                      notes as 'notes!',
+                      deleted,
+                      created,
+                      updated,
+                      completed,
+                      recurrence,
+                      complete_with_subtasks as 'complete_with_subtasks: bool',
+                      estimate",
+                task.description,
+                task.parent,
+                task.due,
+                task.scheduled,
+                task.priority,
+                task.notes,
+                now,
+                recurrence,
+                task.complete_with_subtasks,
+                task.estimate
+            )
+            .fetch_one(&mut transaction)
+            .await?;
+
+            for tag in &task.tags {
+                insert_task_tag(&mut transaction, new_task.id, tag).await?;
+            }
 
-        db.setup().await.unwrap();
+            transaction.commit().await?;
 
-        assert_eq!(db.list_tasks(true).await.unwrap().len(), 0);
+            Ok(new_task)
+        }
+        .await;
+        self.end_change().await?;
 
-        db.add_task("A test task", None).await.unwrap();
+        result
+    }
 
-        for task in db.list_tasks(true).await.unwrap() {
-            println!("Task got ID {}", task.id);
+    /// Adds a tag to a task, creating the tag if nobody has used it before. Tagging a task with a tag
+    /// it already has does nothing
+    pub async fn tag_task(&mut self, task_id: i64, tag: &str) -> Result<(), sqlx::Error> {
+        self.begin_change(&format!("tag task {} with {}", task_id, tag))
+            .await?;
+        let result: Result<(), sqlx::Error> = async {
+            let mut transaction = self.connection.begin().await?;
+            insert_task_tag(&mut transaction, task_id, tag).await?;
+            transaction.commit().await
         }
+        .await;
+        self.end_change().await?;
 
-        assert_eq!(db.list_tasks(true).await.unwrap().len(), 1);
+        result
+    }
 
-        assert_eq!(db.remove_task(1).await.unwrap().len(), 1);
-        assert_eq!(db.remove_task(1).await.unwrap().len(), 0);
+    /// Removes a tag from a task, returning whether the task had the tag
+    pub async fn untag_task(&mut self, task_id: i64, tag: &str) -> Result<bool, sqlx::Error> {
+        self.begin_change(&format!("untag {} from task {}", tag, task_id))
+            .await?;
+        let result: Result<bool, sqlx::Error> = async {
+            let result = sqlx::query!(
+            "DELETE FROM task_tags WHERE task = ? AND tag = (SELECT id FROM tags WHERE name = ?)",
+            task_id,
+            tag
+        )
+            .execute(&mut self.connection)
+            .await?;
 
-        assert_eq!(db.list_tasks(true).await.unwrap().len(), 0);
-    }
+            Ok(result.rows_affected() > 0)
+        }
+        .await;
+        self.end_change().await?;
 
-    #[tokio::test]
-    async fn list_tasks_with_children_test() {
-        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
-            .await
-            .unwrap();
+        result
+    }
 
-        db.setup().await.unwrap();
+    /// Gets the tags of every task that has any, as a map from task ID to the task's tags in
+    /// alphabetical order
+    pub async fn list_task_tags(&mut self) -> Result<HashMap<i64, Vec<String>>, sqlx::Error> {
+        let rows = sqlx::query!(
+            "SELECT task_tags.task as 'task!', tags.name as 'name!'
+            FROM task_tags
+            INNER JOIN tags ON tags.id = task_tags.tag
+            INNER JOIN tasks ON tasks.id = task_tags.task
+            WHERE tasks.deleted IS NULL
+            ORDER BY tags.name"
+        )
+        .fetch_all(&mut self.connection)
+        .await?;
 
-        assert_eq!(db.list_tasks(true).await.unwrap().len(), 0);
+        let mut task_tags: HashMap<i64, Vec<String>> = HashMap::new();
+        for row in rows {
+            task_tags.entry(row.task).or_default().push(row.name);
+        }
 
-        let task = db.add_task("A test task", None).await.unwrap();
-        db.add_task("A child task", Some(task.id)).await.unwrap();
+        Ok(task_tags)
+    }
 
-        assert_eq!(db.list_tasks(true).await.unwrap().len(), 2);
-        assert_eq!(db.list_tasks(false).await.unwrap().len(), 1);
+    /// Lists the tasks whose subtasks are hidden in the task list
+    pub async fn list_collapsed_tasks(&mut self) -> Result<HashSet<i64>, sqlx::Error> {
+        Ok(sqlx::query_scalar!(
+            "SELECT task FROM collapsed_tasks
+            INNER JOIN tasks ON tasks.id = collapsed_tasks.task
+            WHERE tasks.deleted IS NULL"
+        )
+        .fetch_all(&mut self.connection)
+        .await?
+        .into_iter()
+        .collect())
+    }
 
-        db.remove_task(task.id).await.unwrap();
+    /// Hides or shows a task's subtasks in the task list
+    pub async fn set_collapsed(
+        &mut self,
+        task_id: i64,
+        collapsed: bool,
+    ) -> Result<(), sqlx::Error> {
+        if collapsed {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO collapsed_tasks (task) VALUES (?)",
+                task_id
+            )
+            .execute(&mut self.connection)
+            .await?;
+        } else {
+            sqlx::query!("DELETE FROM collapsed_tasks WHERE task = ?", task_id)
+                .execute(&mut self.connection)
+                .await?;
+        }
 
-        // Ensure that subtasks are properly cascade-deleted
-        assert_eq!(db.list_tasks(true).await.unwrap().len(), 0);
+        Ok(())
     }
 
-    #[tokio::test]
-    #[should_panic]
-    /// This is a regression test, previously this was the single way to add a cycle to the 'tree'.
-    /// As that's now patched we should never be able to get cycles so long as the user doesn't
+    /// Lists every task with the given tag, including subtasks
+    pub async fn list_tasks_with_tag(&mut self, tag: &str) -> Result<Vec<Task>, sqlx::Error> {
+        sqlx::query_as!(
+            Task,
+            "SELECT tasks.* FROM tasks
+            INNER JOIN task_tags ON task_tags.task = tasks.id
+            INNER JOIN tags ON tags.id = task_tags.tag
+            WHERE tags.name = ? AND tasks.deleted IS NULL
+            ORDER BY tasks.id",
+            tag
+        )
+        .fetch_all(&mut self.connection)
+        .await
+    }
+
+    /// Marks a task as blocked by another, so that it can't be done until the other task is.
+    /// Blocking a task by a task which already blocks it does nothing
+    ///
+    /// A task can't be blocked by itself or by any task it's blocking, directly or not. This is also
+    /// enforced by a trigger in the database, but checking here first lets us give a more specific
+    /// error
+    pub async fn block_task(
+        &mut self,
+        task_id: i64,
+        blocked_by: i64,
+    ) -> Result<(), BlockTaskError> {
+        self.begin_change(&format!("block task {} by {}", task_id, blocked_by))
+            .await?;
+        let result: Result<(), BlockTaskError> = async {
+            // Tasks in the trash can't be used, as though they didn't exist
+            for id in [task_id, blocked_by] {
+                self.get_task(id).await?;
+            }
+
+            let cycle = sqlx::query_scalar!(
+                "WITH RECURSIVE blockers AS (
+                    SELECT ?1 AS id
+                UNION
+                    SELECT task_dependencies.blocked_by
+                    FROM task_dependencies
+                INNER JOIN blockers ON blockers.id = task_dependencies.task
+            )
+            SELECT COUNT(1) FROM blockers WHERE id = ?2",
+                blocked_by,
+                task_id
+            )
+            .fetch_one(&mut self.connection)
+            .await?;
+
+            if cycle > 0 {
+                return Err(BlockTaskError::Cycle);
+            }
+
+            sqlx::query!(
+                "INSERT OR IGNORE INTO task_dependencies (task, blocked_by) VALUES (?, ?)",
+                task_id,
+                blocked_by
+            )
+            .execute(&mut self.connection)
+            .await?;
+
+            Ok(())
+        }
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    /// Stops a task from being blocked by another, returning whether it was
+    pub async fn unblock_task(
+        &mut self,
+        task_id: i64,
+        blocked_by: i64,
+    ) -> Result<bool, sqlx::Error> {
+        self.begin_change(&format!("unblock task {} from {}", task_id, blocked_by))
+            .await?;
+        let result: Result<bool, sqlx::Error> = async {
+            let result = sqlx::query!(
+                "DELETE FROM task_dependencies WHERE task = ? AND blocked_by = ?",
+                task_id,
+                blocked_by
+            )
+            .execute(&mut self.connection)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        }
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    /// Gets the tasks blocking each task which is blocked, as a map from task ID to the IDs of the
+    /// tasks blocking it. Only open blockers count, so tasks which are done or in the trash are left
+    /// out, and tasks with none left aren't in the map at all
+    pub async fn list_open_blockers(&mut self) -> Result<HashMap<i64, Vec<i64>>, sqlx::Error> {
+        let rows = sqlx::query!(
+            "SELECT task_dependencies.task as 'task!', task_dependencies.blocked_by as 'blocked_by!'
+            FROM task_dependencies
+            INNER JOIN tasks ON tasks.id = task_dependencies.task
+            INNER JOIN tasks blockers ON blockers.id = task_dependencies.blocked_by
+            WHERE tasks.deleted IS NULL AND blockers.deleted IS NULL AND NOT blockers.complete
+            ORDER BY task_dependencies.blocked_by"
+        )
+        .fetch_all(&mut self.connection)
+        .await?;
+
+        let mut blockers: HashMap<i64, Vec<i64>> = HashMap::new();
+        for row in rows {
+            blockers.entry(row.task).or_default().push(row.blocked_by);
+        }
+
+        Ok(blockers)
+    }
+
+    /// Starts timing a task, returning the new timer along with the timer that had to be stopped to
+    /// start it, if there was one. Only one timer runs at a time, so starting a task's timer while
+    /// it's already running leaves it as it is and returns it
+    pub async fn start_timer(
+        &mut self,
+        task_id: i64,
+    ) -> Result<(TimeEntry, Option<TimeEntry>), sqlx::Error> {
+        self.begin_change(&format!("start timing task {}", task_id))
+            .await?;
+        let now = Local::now().timestamp();
+        let result: Result<(TimeEntry, Option<TimeEntry>), sqlx::Error> = async {
+            self.get_task(task_id).await?;
+
+            if let Some(running) = self.running_timer().await? {
+                if running.task == task_id {
+                    return Ok((running, None));
+                }
+            }
+            let stopped = self.stop_timer().await?;
+
+            let started = sqlx::query_as!(
+                TimeEntry,
+                "INSERT INTO time_entries (task, started) VALUES (?, ?)
+                RETURNING id as 'id!', task as 'task!', started as 'started!', stopped",
+                task_id,
+                now
+            )
+            .fetch_one(&mut self.connection)
+            .await?;
+
+            Ok((started, stopped))
+        }
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    /// Stops the running timer, returning it, or None if no timer was running
+    pub async fn stop_timer(&mut self) -> Result<Option<TimeEntry>, sqlx::Error> {
+        self.begin_change("stop timer").await?;
+        let now = Local::now().timestamp();
+        let result = sqlx::query_as!(
+            TimeEntry,
+            "UPDATE time_entries SET stopped = MAX(?, started) WHERE stopped IS NULL
+            RETURNING id as 'id!', task as 'task!', started as 'started!', stopped",
+            now
+        )
+        .fetch_optional(&mut self.connection)
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    /// Gets the timer that's running, if there is one
+    pub async fn running_timer(&mut self) -> Result<Option<TimeEntry>, sqlx::Error> {
+        sqlx::query_as!(
+            TimeEntry,
+            "SELECT * FROM time_entries WHERE stopped IS NULL"
+        )
+        .fetch_optional(&mut self.connection)
+        .await
+    }
+
+    /// Records time spent on a task without timing it, such as time that was forgotten about,
+    /// starting at `started` and lasting `seconds`
+    pub async fn log_time(
+        &mut self,
+        task_id: i64,
+        started: i64,
+        seconds: i64,
+    ) -> Result<TimeEntry, sqlx::Error> {
+        self.begin_change(&format!("log time on task {}", task_id))
+            .await?;
+        let result: Result<TimeEntry, sqlx::Error> = async {
+            self.get_task(task_id).await?;
+
+            let stopped = started + seconds;
+            sqlx::query_as!(
+                TimeEntry,
+                "INSERT INTO time_entries (task, started, stopped) VALUES (?, ?, ?)
+                RETURNING id as 'id!', task as 'task!', started as 'started!', stopped",
+                task_id,
+                started,
+                stopped
+            )
+            .fetch_one(&mut self.connection)
+            .await
+        }
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    /// Adds up the seconds spent on each task, as a map from task ID to the time spent on the task
+    /// itself and the time spent on it along with all of its subtasks. A running timer counts up
+    /// until `now`. Tasks without any time spent on them or their subtasks aren't in the map, and
+    /// tasks in the trash aren't counted
+    pub async fn list_time_spent(
+        &mut self,
+        now: i64,
+    ) -> Result<HashMap<i64, (i64, i64)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            "WITH RECURSIVE spent(task, seconds, own) AS (
+                SELECT time_entries.task, COALESCE(time_entries.stopped, ?1) - time_entries.started, true
+                FROM time_entries
+                INNER JOIN tasks ON tasks.id = time_entries.task
+                WHERE tasks.deleted IS NULL
+            UNION ALL
+                SELECT tasks.parent, spent.seconds, false
+                FROM spent
+            INNER JOIN tasks ON tasks.id = spent.task
+            WHERE tasks.parent IS NOT NULL AND tasks.deleted IS NULL
+        )
+        SELECT task as 'task!: i64',
+               SUM(CASE WHEN own THEN seconds ELSE 0 END) as 'own!: i64',
+               SUM(seconds) as 'with_subtasks!: i64'
+        FROM spent
+        GROUP BY task",
+            now
+        )
+        .fetch_all(&mut self.connection)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.task, (row.own, row.with_subtasks)))
+            .collect())
+    }
+
+    /// Adds up the seconds spent on each task between `from` and `to`, as a map from task ID to
+    /// seconds. Only the part of an entry inside the range counts, a running timer counts up until
+    /// `now`, and tasks in the trash are left out
+    pub async fn list_time_between(
+        &mut self,
+        from: i64,
+        to: i64,
+        now: i64,
+    ) -> Result<HashMap<i64, i64>, sqlx::Error> {
+        let rows = sqlx::query!(
+            "SELECT time_entries.task as 'task!: i64',
+                    SUM(MIN(COALESCE(time_entries.stopped, ?3), ?2) - MAX(time_entries.started, ?1)) as 'seconds!: i64'
+            FROM time_entries
+            INNER JOIN tasks ON tasks.id = time_entries.task
+            WHERE tasks.deleted IS NULL
+              AND time_entries.started < ?2
+              AND COALESCE(time_entries.stopped, ?3) > ?1
+            GROUP BY time_entries.task",
+            from,
+            to,
+            now
+        )
+        .fetch_all(&mut self.connection)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.task, row.seconds))
+            .collect())
+    }
+
+    /// Moves a task, along with all of its subtasks, to the trash, returning every task that was
+    /// moved. This is empty if the task was not found or is already in the trash
+    ///
+    /// Tasks in the trash are left out of everything else, but can be brought back with
+    /// [`Database::restore_task`] until they're deleted for good by [`Database::purge_trash`]
+    pub async fn remove_task(&mut self, task_id: i64) -> Result<Vec<Task>, sqlx::Error> {
+        self.begin_change(&format!("remove task {}", task_id))
+            .await?;
+        let now = Local::now().timestamp();
+        let result: Result<Vec<Task>, sqlx::Error> = async {
+            sqlx::query_as!(
+                Task,
+                "WITH RECURSIVE subtask_tree AS (
+                SELECT id
+                FROM tasks
+                WHERE id = ?1 AND deleted IS NULL
+            UNION ALL
+                SELECT subtasks.id
+                FROM tasks subtasks
+            INNER JOIN subtask_tree ON subtask_tree.id = subtasks.parent
+            WHERE subtasks.deleted IS NULL
+        )
+        UPDATE tasks SET deleted = ?2, updated = ?2 WHERE id IN (SELECT id FROM subtask_tree)
+            RETURNING id as 'id!',
+                      description as 'description!',
+                      complete as 'complete!',
+                      parent,
+                      due,
+                      scheduled,
+                      priority as 'priority!',
+                      notes as 'notes!',
+                      deleted,
+                      created,
+                      updated,
+                      completed,
+                      recurrence,
+                      complete_with_subtasks as 'complete_with_subtasks: bool',
+                      estimate",
+                task_id,
+                now
+            )
+            .fetch_all(&mut self.connection)
+            .await
+        }
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    /// Lists every task in the trash, as trees of the tasks which were removed along with them. The
+    /// most recently removed come first
+    pub async fn list_trash(&mut self) -> Result<Vec<TaskTree>, sqlx::Error> {
+        let tasks = sqlx::query_as!(
+            Task,
+            "SELECT id as 'id!',
+                    description as 'description!',
+                    complete as 'complete!',
+                    parent,
+                    due,
+                    scheduled,
+                    priority as 'priority!',
+                    notes as 'notes!',
+                    deleted,
+                    created,
+                    updated,
+                    completed,
+                    recurrence,
+                    complete_with_subtasks as 'complete_with_subtasks: bool',
+                    estimate
+            FROM tasks
+            WHERE deleted IS NOT NULL
+            ORDER BY deleted DESC, id"
+        )
+        .fetch_all(&mut self.connection)
+        .await?;
+
+        let mut children: HashMap<i64, Vec<Task>> = HashMap::new();
+        for task in &tasks {
+            if let Some(parent) = task.parent {
+                children.entry(parent).or_default().push(task.clone());
+            }
+        }
+
+        Ok(tasks
+            .iter()
+            .filter(|task| {
+                !task
+                    .parent
+                    .is_some_and(|parent| tasks.iter().any(|other| other.id == parent))
+            })
+            .map(|task| {
+                TaskAndChildMap {
+                    task,
+                    child_map: &children,
+                    level: 0,
+                }
+                .into()
+            })
+            .collect())
+    }
+
+    /// Brings a task back out of the trash with the same ID and parent it had, along with the
+    /// subtasks that were removed with it. If its parent is in the trash too, the parent is brought
+    /// back so that the task has somewhere to go. Returns every task that was restored, which is
+    /// empty if the task isn't in the trash
+    pub async fn restore_task(&mut self, task_id: i64) -> Result<Vec<Task>, sqlx::Error> {
+        self.begin_change(&format!("restore task {}", task_id))
+            .await?;
+        let now = Local::now().timestamp();
+        let result = sqlx::query_as!(
+            Task,
+            "WITH RECURSIVE subtask_tree AS (
+                SELECT id, deleted
+                FROM tasks
+                WHERE id = ?1
+            UNION ALL
+                SELECT subtasks.id, subtasks.deleted
+                FROM tasks subtasks
+            INNER JOIN subtask_tree ON subtask_tree.id = subtasks.parent
+            WHERE subtasks.deleted = subtask_tree.deleted
+        ),
+        parent_chain AS (
+                SELECT parent AS id
+                FROM tasks
+                WHERE id = ?1
+            UNION ALL
+                SELECT tasks.parent
+                FROM tasks
+            INNER JOIN parent_chain ON parent_chain.id = tasks.id
+        )
+        UPDATE tasks SET deleted = NULL, updated = ?2
+        WHERE deleted IS NOT NULL
+          AND (id IN (SELECT id FROM subtask_tree) OR id IN (SELECT id FROM parent_chain))
+            RETURNING id as 'id!',
+                      description as 'description!',
+                      complete as 'complete!',
+                      parent,
+                      due,
+                      scheduled,
+                      priority as 'priority!',
+                      notes as 'notes!',
+                      deleted,
+                      created,
+                      updated,
+                      completed,
+                      recurrence,
+                      complete_with_subtasks as 'complete_with_subtasks: bool',
+                      estimate",
+            task_id,
+            now
+        )
+        .fetch_all(&mut self.connection)
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    /// Deletes every task which was moved to the trash before the given time for good, along with
+    /// their subtasks, returning the tasks that were deleted
+    pub async fn purge_trash(&mut self, removed_before: i64) -> Result<Vec<Task>, sqlx::Error> {
+        self.begin_change("purge the trash").await?;
+        let result = sqlx::query_as!(
+            Task,
+            "WITH RECURSIVE subtask_tree AS (
+                SELECT id
+                FROM tasks
+                WHERE deleted < ?
+            UNION ALL
+                SELECT subtasks.id
+                FROM tasks subtasks
+            INNER JOIN subtask_tree ON subtask_tree.id = subtasks.parent
+        )
+        DELETE FROM tasks WHERE id IN (SELECT id FROM subtask_tree)
+            RETURNING id as 'id!',
+                      description as 'description!',
+                      complete as 'complete!',
+                      parent,
+                      due,
+                      scheduled,
+                      priority as 'priority!',
+                      notes as 'notes!',
+                      deleted,
+                      created,
+                      updated,
+                      completed,
+                      recurrence,
+                      complete_with_subtasks as 'complete_with_subtasks: bool',
+                      estimate",
+            removed_before
+        )
+        .fetch_all(&mut self.connection)
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    /// Marks a task as done or not done. If a task which repeats is marked as done, a copy of it is
+    /// made for next time (see [`Database::repeat_task`]), which is returned along with the task.
+    /// The parents which were marked along with it are returned last (see
+    /// [`Database::update_parent_completion`])
+    pub async fn set_completion(
+        &mut self,
+        index: i64,
+        completed: bool,
+    ) -> Result<(Task, Option<Task>, Vec<Task>), sqlx::Error> {
+        self.begin_change(&format!(
+            "{} task {}",
+            if completed { "complete" } else { "uncomplete" },
+            index
+        ))
+        .await?;
+        let now = Local::now();
+        let result: Result<(Task, Option<Task>, Vec<Task>), sqlx::Error> = async {
+            let was_complete = self.is_complete(index).await?;
+            let now_timestamp = now.timestamp();
+            let mut task = self
+                .update_completion(index, completed, now_timestamp)
+                .await?;
+
+            let next = if completed && was_complete == Some(false) {
+                self.repeat_task(index, now).await?
+            } else {
+                None
+            };
+            if next.is_some() {
+                task.recurrence = None;
+            }
+            // This comes after repeating the task, as the copy for next time isn't done yet
+            let parents = self.update_parent_completion(index, now_timestamp).await?;
+
+            Ok((task, next, parents))
+        }
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    /// Marks a single task as done or not done, without touching its subtasks or parents
+    async fn update_completion(
+        &mut self,
+        task_id: i64,
+        completed: bool,
+        now: i64,
+    ) -> Result<Task, sqlx::Error> {
+        sqlx::query_as!(
+            Task,
+            "UPDATE tasks SET complete = ?1,
+                              completed = CASE WHEN NOT ?1 THEN NULL
+                                               WHEN complete THEN completed
+                                               ELSE ?2 END,
+                              updated = ?2
+                          WHERE id = ?3 AND deleted IS NULL
+                    RETURNING id as 'id!',
+                              description as 'description!',
+                              complete as 'complete!',
+                              parent,
+                              due,
+                              scheduled,
+                              priority as 'priority!',
+                              notes as 'notes!',
+                              deleted,
+                              created,
+                              updated,
+                              completed,
+                              recurrence,
+                              complete_with_subtasks as 'complete_with_subtasks: bool',
+                              estimate",
+            completed,
+            now,
+            task_id
+        )
+        .fetch_one(&mut self.connection)
+        .await
+    }
+
+    /// Marks a task's parent as done once all of its subtasks are, or as not done once one of them
+    /// isn't, and then does the same for that parent's parent and so on, returning the parents which
+    /// changed. Only parents which complete with their subtasks are changed (see
+    /// [`Task::complete_with_subtasks`]), and it stops at the first parent which isn't
+    async fn update_parent_completion(
+        &mut self,
+        task_id: i64,
+        now: i64,
+    ) -> Result<Vec<Task>, sqlx::Error> {
+        let complete_with_subtasks = config::get().tasks.complete_with_subtasks;
+        let mut changed_parents = vec![];
+        let mut task_id = task_id;
+
+        while let Some(parent) = sqlx::query_as!(
+            Task,
+            "SELECT * FROM tasks
+            WHERE id = (SELECT parent FROM tasks WHERE id = ? AND deleted IS NULL)
+                AND deleted IS NULL",
+            task_id
+        )
+        .fetch_optional(&mut self.connection)
+        .await?
+        {
+            if !parent
+                .complete_with_subtasks
+                .unwrap_or(complete_with_subtasks)
+            {
+                break;
+            }
+
+            let subtasks_done = sqlx::query_scalar!(
+                "SELECT COUNT(1) = 0 as 'done!: bool'
+                FROM tasks
+                WHERE parent = ? AND deleted IS NULL AND NOT complete",
+                parent.id
+            )
+            .fetch_one(&mut self.connection)
+            .await?;
+            if subtasks_done == parent.complete {
+                break;
+            }
+
+            let parent = self
+                .update_completion(parent.id, subtasks_done, now)
+                .await?;
+            task_id = parent.id;
+            changed_parents.push(parent);
+        }
+
+        Ok(changed_parents)
+    }
+
+    /// Counts how many of each task's subtasks are done and how many subtasks it has, including the
+    /// subtasks of its subtasks and so on. Tasks without any subtasks aren't in the map, and tasks in
+    /// the trash aren't counted
+    pub async fn list_progress(&mut self) -> Result<HashMap<i64, (usize, usize)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            "WITH RECURSIVE subtasks(task, complete) AS (
+                SELECT parent, complete
+                FROM tasks
+                WHERE parent IS NOT NULL AND deleted IS NULL
+            UNION ALL
+                SELECT tasks.parent, subtasks.complete
+                FROM subtasks
+            INNER JOIN tasks ON tasks.id = subtasks.task
+            WHERE tasks.parent IS NOT NULL AND tasks.deleted IS NULL
+        )
+        SELECT task as 'task!: i64', SUM(complete) as 'done!: i64', COUNT(1) as 'total!: i64'
+        FROM subtasks
+        GROUP BY task"
+        )
+        .fetch_all(&mut self.connection)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.task, (row.done as usize, row.total as usize)))
+            .collect())
+    }
+
+    /// Whether a task is done, or None if it doesn't exist or is in the trash
+    async fn is_complete(&mut self, task_id: i64) -> Result<Option<bool>, sqlx::Error> {
+        sqlx::query_scalar!(
+            "SELECT complete as 'complete!: bool' FROM tasks WHERE id = ? AND deleted IS NULL",
+            task_id
+        )
+        .fetch_optional(&mut self.connection)
+        .await
+    }
+
+    /// Makes a copy of a repeating task and its subtasks for the next time it comes back, moving the
+    /// task's rule over to the copy, and returns the copy. Nothing is copied if the task doesn't
+    /// repeat
+    ///
+    /// The copies aren't done, and their dates are moved forward by however far the task's own due
+    /// (or scheduled) date moves. A task without either date has its copy scheduled for next time
+    async fn repeat_task(
+        &mut self,
+        task_id: i64,
+        now: DateTime<Local>,
+    ) -> Result<Option<Task>, sqlx::Error> {
+        // Parents always come before their subtasks here, so they're copied first
+        let tasks = sqlx::query_as!(
+            Task,
+            "WITH RECURSIVE subtask_tree AS (
+                SELECT *
+                FROM tasks
+                WHERE id = ? AND deleted IS NULL
+            UNION ALL
+                SELECT subtasks.*
+                FROM tasks subtasks
+            INNER JOIN subtask_tree ON subtask_tree.id = subtasks.parent
+            WHERE subtasks.deleted IS NULL
+        )
+        SELECT id as 'id!',
+               description as 'description!',
+               complete as 'complete!',
+               parent,
+               due,
+               scheduled,
+               priority as 'priority!',
+               notes as 'notes!',
+               deleted,
+               created,
+               updated,
+               completed,
+               recurrence,
+               complete_with_subtasks as 'complete_with_subtasks: bool',
+               estimate
+        FROM subtask_tree",
+            task_id
+        )
+        .fetch_all(&mut self.connection)
+        .await?;
+
+        let Some(root) = tasks.first() else {
+            return Ok(None);
+        };
+        let Some(recurrence) = root
+            .recurrence
+            .as_deref()
+            .and_then(|rule| Recurrence::parse(rule).ok())
+        else {
+            return Ok(None);
+        };
+        let date = root.due.or(root.scheduled);
+        let Some(next_date) = recurrence.next_date(date, now) else {
+            return Ok(None);
+        };
+        let offset = next_date - date.unwrap_or_else(|| now.timestamp());
+        let now = now.timestamp();
+
+        let mut transaction = self.connection.begin().await?;
+        let mut copied_ids: HashMap<i64, i64> = HashMap::new();
+        let mut copy_of_root = None;
+        for task in &tasks {
+            let is_root = task.id == root.id;
+            let parent = if is_root {
+                task.parent
+            } else {
+                task.parent
+                    .and_then(|parent| copied_ids.get(&parent).copied())
+            };
+            let due = task.due.map(|due| due + offset);
+            let scheduled = match task.scheduled {
+                Some(scheduled) => Some(scheduled + offset),
+                None if is_root && date.is_none() => Some(next_date),
+                None => None,
+            };
+            // Only the copy of the task itself repeats, its subtasks come along with it
+            let recurrence = if is_root {
+                root.recurrence.clone()
+            } else {
+                None
+            };
+
+            let copy = sqlx::query_as!(
+                Task,
+                "INSERT INTO tasks (description, complete, parent, due, scheduled, priority, notes, created, updated, recurrence, complete_with_subtasks, estimate)
+                VALUES (?1, false, ?2, ?3, ?4, ?5, ?6, ?7, ?7, ?8, ?9, ?10)
+                RETURNING id as 'id!',
+                          description as 'description!',
+                          complete as 'complete!',
+                          parent,
+                          due,
+                          scheduled,
+                          priority as 'priority!',
+                          notes as 'notes!',
+                          deleted,
+                          created,
+                          updated,
+                          completed,
+                          recurrence,
+                          complete_with_subtasks as 'complete_with_subtasks: bool',
+                          estimate",
+                task.description,
+                parent,
+                due,
+                scheduled,
+                task.priority,
+                task.notes,
+                now,
+                recurrence,
+                task.complete_with_subtasks,
+                task.estimate
+            )
+            .fetch_one(&mut transaction)
+            .await?;
+
+            sqlx::query!(
+                "INSERT INTO task_tags (task, tag) SELECT ?, tag FROM task_tags WHERE task = ?",
+                copy.id,
+                task.id
+            )
+            .execute(&mut transaction)
+            .await?;
+
+            copied_ids.insert(task.id, copy.id);
+            if is_root {
+                copy_of_root = Some(copy);
+            }
+        }
+
+        sqlx::query!(
+            "UPDATE tasks SET recurrence = NULL, updated = ? WHERE id = ?",
+            now,
+            root.id
+        )
+        .execute(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+
+        Ok(copy_of_root)
+    }
+
+    /// Changes any of a task's fields at once, returning the updated task
+    pub async fn update_task(
+        &mut self,
+        task_id: i64,
+        update: &TaskUpdate,
+    ) -> Result<Task, sqlx::Error> {
+        self.begin_change(&format!("edit task {}", task_id)).await?;
+        let now = Local::now().timestamp();
+        let result: Result<Task, sqlx::Error> = async {
+            let (update_due, due) = (update.due.is_some(), update.due.flatten());
+            let (update_scheduled, scheduled) =
+                (update.scheduled.is_some(), update.scheduled.flatten());
+            let (update_recurrence, recurrence) = (
+                update.recurrence.is_some(),
+                update
+                    .recurrence
+                    .clone()
+                    .flatten()
+                    .map(|rule| rule.to_string()),
+            );
+            let (update_complete_with_subtasks, complete_with_subtasks) = (
+                update.complete_with_subtasks.is_some(),
+                update.complete_with_subtasks.flatten(),
+            );
+            let (update_estimate, estimate) = (update.estimate.is_some(), update.estimate.flatten());
+
+            sqlx::query_as!(
+                Task,
+                "UPDATE tasks SET description = COALESCE(?, description),
+                              due = CASE WHEN ? THEN ? ELSE due END,
+                              scheduled = CASE WHEN ? THEN ? ELSE scheduled END,
+                              priority = COALESCE(?, priority),
+                              notes = COALESCE(?, notes),
+                              recurrence = CASE WHEN ? THEN ? ELSE recurrence END,
+                              complete_with_subtasks = CASE WHEN ? THEN ? ELSE complete_with_subtasks END,
+                              estimate = CASE WHEN ? THEN ? ELSE estimate END,
+                              updated = ?
+                          WHERE id = ? AND deleted IS NULL
+                        RETURNING id as 'id!',
+                                  description as 'description!',
+                                  complete as 'complete!',
+                                  parent,
+                                  due,
+                                  scheduled,
+                                  priority as 'priority!',
+                                  notes as 'notes!',
+                                  deleted,
+                                  created,
+                                  updated,
+                                  completed,
+                                  recurrence,
+                                  complete_with_subtasks as 'complete_with_subtasks: bool',
+                                  estimate",
+                update.description,
+                update_due,
+                due,
+                update_scheduled,
+                scheduled,
+                update.priority,
+                update.notes,
+                update_recurrence,
+                recurrence,
+                update_complete_with_subtasks,
+                complete_with_subtasks,
+                update_estimate,
+                estimate,
+                now,
+                task_id
+            )
+            .fetch_one(&mut self.connection)
+            .await
+        }
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    /// Moves a task, along with all of its subtasks, under a different parent. Passing `None` as the
+    /// parent makes it a top-level task
+    ///
+    /// Moving a task under itself or one of its own subtasks is refused. This is also enforced by a
+    /// trigger in the database, but checking here first lets us give a more specific error
+    pub async fn move_task(
+        &mut self,
+        task_id: i64,
+        new_parent: Option<i64>,
+    ) -> Result<Task, MoveTaskError> {
+        self.begin_change(&format!("move task {}", task_id)).await?;
+        let now = Local::now().timestamp();
+        let result: Result<Task, MoveTaskError> = async {
+            if let Some(new_parent) = new_parent {
+                let cycle = sqlx::query_scalar!(
+                    "WITH RECURSIVE subtask_tree AS (
+                    SELECT id
+                    FROM tasks
+                    WHERE id = ?
+                UNION ALL
+                    SELECT subtasks.id
+                    FROM tasks subtasks
+                INNER JOIN subtask_tree ON subtask_tree.id = subtasks.parent
+            )
+            SELECT COUNT(1) FROM subtask_tree WHERE id = ?",
+                    task_id,
+                    new_parent
+                )
+                .fetch_one(&mut self.connection)
+                .await?;
+
+                if cycle > 0 {
+                    return Err(MoveTaskError::Cycle);
+                }
+            }
+
+            Ok(sqlx::query_as!(
+                Task,
+                "UPDATE tasks SET parent = ?, updated = ? WHERE id = ? AND deleted IS NULL
+                        RETURNING id as 'id!',
+                                  description as 'description!',
+                                  complete as 'complete!',
+                                  parent,
+                                  due,
+                                  scheduled,
+                                  priority as 'priority!',
+                                  notes as 'notes!',
+                                  deleted,
+                                  created,
+                                  updated,
+                                  completed,
+                                  recurrence,
+                                  complete_with_subtasks as 'complete_with_subtasks: bool',
+                                  estimate",
+                new_parent,
+                now,
+                task_id
+            )
+            .fetch_one(&mut self.connection)
+            .await?)
+        }
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    /// Sets the completion of a task and every one of its subtasks in a single query, returning all
+    /// of the tasks that were changed. This will be empty if the task was not found. As with
+    /// [`Database::set_completion`], the copy made for next time is returned too if the task repeats,
+    /// followed by the parents which were marked along with it
+    pub async fn set_subtree_completion(
+        &mut self,
+        task_id: i64,
+        completed: bool,
+    ) -> Result<(Vec<Task>, Option<Task>, Vec<Task>), sqlx::Error> {
+        self.begin_change(&format!(
+            "{} task {}",
+            if completed { "complete" } else { "uncomplete" },
+            task_id
+        ))
+        .await?;
+        let now = Local::now();
+        let result: Result<(Vec<Task>, Option<Task>, Vec<Task>), sqlx::Error> = async {
+            let was_complete = self.is_complete(task_id).await?;
+            let now_timestamp = now.timestamp();
+            let mut tasks = sqlx::query_as!(
+                Task,
+                "WITH RECURSIVE subtask_tree AS (
+                SELECT *
+                FROM tasks
+                WHERE id = ?1 AND deleted IS NULL
+            UNION ALL
+                SELECT subtasks.*
+                FROM tasks subtasks
+            INNER JOIN subtask_tree ON subtask_tree.id = subtasks.parent
+            WHERE subtasks.deleted IS NULL
+        )
+        UPDATE tasks SET complete = ?2,
+                         completed = CASE WHEN NOT ?2 THEN NULL
+                                          WHEN complete THEN completed
+                                          ELSE ?3 END,
+                         updated = ?3
+                     WHERE id IN (SELECT id FROM subtask_tree)
+            RETURNING id as 'id!',
+                      description as 'description!',
+                      complete as 'complete!',
+                      parent,
+                      due,
+                      scheduled,
+                      priority as 'priority!',
+                      notes as 'notes!',
+                      deleted,
+                      created,
+                      updated,
+                      completed,
+                      recurrence,
+                      complete_with_subtasks as 'complete_with_subtasks: bool',
+                      estimate",
+                task_id,
+                completed,
+                now_timestamp
+            )
+            .fetch_all(&mut self.connection)
+            .await?;
+
+            let next = if completed && was_complete == Some(false) {
+                self.repeat_task(task_id, now).await?
+            } else {
+                None
+            };
+            if next.is_some() {
+                for task in tasks.iter_mut().filter(|task| task.id == task_id) {
+                    task.recurrence = None;
+                }
+            }
+            let parents = self
+                .update_parent_completion(task_id, now_timestamp)
+                .await?;
+
+            Ok((tasks, next, parents))
+        }
+        .await;
+        self.end_change().await?;
+
+        result
+    }
+
+    pub async fn get_task(&mut self, task_id: i64) -> Result<Task, sqlx::Error> {
+        sqlx::query_as!(
+            Task,
+            "SELECT * FROM tasks WHERE id = ? AND deleted IS NULL",
+            task_id
+        )
+        .fetch_one(&mut self.connection)
+        .await
+    }
+
+    pub async fn list_tasks(&mut self, include_children: bool) -> Result<Vec<Task>, sqlx::Error> {
+        if include_children {
+            sqlx::query_as!(Task, "SELECT * FROM tasks WHERE deleted IS NULL")
+                .fetch_all(&mut self.connection)
+                .await
+        } else {
+            sqlx::query_as!(
+                Task,
+                "SELECT * FROM tasks WHERE parent IS NULL AND deleted IS NULL"
+            )
+            // == null is invalid (https://www.sqlitetutorial.net/sqlite-is-null/)
+            .fetch_all(&mut self.connection)
+            .await
+        }
+    }
+
+    /// Lists the IDs of a task and all of its parents, starting from the top-level task and ending
+    /// with the task itself. This is empty if the task doesn't exist or is in the trash
+    pub async fn list_path(&mut self, task_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar!(
+            "WITH RECURSIVE parent_chain(id, parent, depth) AS (
+                    SELECT id, parent, 0
+                    FROM tasks
+                    WHERE id = ? AND deleted IS NULL
+                UNION ALL
+                    SELECT tasks.id, tasks.parent, parent_chain.depth + 1
+                    FROM tasks
+                INNER JOIN parent_chain ON parent_chain.parent = tasks.id
+            )
+            SELECT id as 'id!' FROM parent_chain ORDER BY depth DESC",
+            task_id
+        )
+        .fetch_all(&mut self.connection)
+        .await
+    }
+
+    pub async fn list_subtasks(&mut self, task_id: i64) -> eyre::Result<TaskTree> {
+        let tasks = sqlx::query_as!(
+            Task,
+            "WITH RECURSIVE subtask_tree AS (
+                SELECT *
+                FROM tasks
+                WHERE id = ? AND deleted IS NULL
+            UNION ALL
+                SELECT subtasks.*
+                FROM tasks subtasks
+            INNER JOIN subtask_tree ON subtask_tree.id = subtasks.parent
+            WHERE subtasks.deleted IS NULL
+        )
+        SELECT id as 'id!',
+               description as 'description!',
+               complete as 'complete!',
+               parent,
+               due,
+               scheduled,
+               priority as 'priority!',
+               notes as 'notes!',
+               deleted,
+               created,
+               updated,
+               completed,
+               recurrence,
+               complete_with_subtasks as 'complete_with_subtasks: bool',
+               estimate
+        FROM subtask_tree",
+            task_id
+        )
+        .fetch_all(&mut self.connection)
+        .await?;
+        tasks.try_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn add_task_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 0);
+
+        db.add_task(&NewTask::new("A test task", None))
+            .await
+            .unwrap();
+
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn remove_task_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 0);
+
+        db.add_task(&NewTask::new("A test task", None))
+            .await
+            .unwrap();
+
+        for task in db.list_tasks(true).await.unwrap() {
+            println!("Task got ID {}", task.id);
+        }
+
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 1);
+
+        assert_eq!(db.remove_task(1).await.unwrap().len(), 1);
+        assert_eq!(db.remove_task(1).await.unwrap().len(), 0);
+
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn trash_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let parent = db
+            .add_task(&NewTask::new("A parent task", None))
+            .await
+            .unwrap();
+        let child = db
+            .add_task(&NewTask::new("A child task", Some(parent.id)))
+            .await
+            .unwrap();
+        let grandchild = db
+            .add_task(&NewTask::new("A grandchild task", Some(child.id)))
+            .await
+            .unwrap();
+
+        // The grandchild was removed on its own first, so restoring the child leaves it in the trash
+        assert_eq!(db.remove_task(grandchild.id).await.unwrap().len(), 1);
+        sqlx::query("UPDATE tasks SET deleted = deleted - 60")
+            .execute(db.connection())
+            .await
+            .unwrap();
+        assert_eq!(db.remove_task(parent.id).await.unwrap().len(), 2);
+        assert!(db.list_tasks(true).await.unwrap().is_empty());
+        assert!(db.get_task(child.id).await.is_err());
+
+        let trash = db.list_trash().await.unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].id, parent.id);
+        assert_eq!(trash[0].children[0].children[0].id, grandchild.id);
+
+        // Nothing can be added under a task in the trash
+        assert!(db
+            .add_task(&NewTask::new("Another task", Some(parent.id)))
+            .await
+            .is_err());
+
+        // Restoring a subtask brings its parent back too, with their original ids
+        let restored = db.restore_task(child.id).await.unwrap();
+        assert_eq!(
+            restored.iter().map(|task| task.id).collect::<Vec<_>>(),
+            vec![parent.id, child.id]
+        );
+        assert_eq!(db.get_task(child.id).await.unwrap().parent, Some(parent.id));
+        assert!(db.restore_task(child.id).await.unwrap().is_empty());
+
+        let now = Local::now().timestamp();
+        assert!(db.purge_trash(now - 120).await.unwrap().is_empty());
+        assert_eq!(db.purge_trash(now + 1).await.unwrap().len(), 1);
+        assert!(db.list_trash().await.unwrap().is_empty());
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn task_timestamps_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let task = db
+            .add_task(&NewTask::new("A test task", None))
+            .await
+            .unwrap();
+        assert!(task.created.is_some());
+        assert_eq!(task.updated, task.created);
+        assert_eq!(task.completed, None);
+
+        let (task, _, _) = db.set_completion(task.id, true).await.unwrap();
+        let completed = task.completed;
+        assert!(completed.is_some());
+        // Marking a task as done again shouldn't change when it was done
+        assert_eq!(
+            db.set_completion(task.id, true).await.unwrap().0.completed,
+            completed
+        );
+        assert_eq!(
+            db.set_completion(task.id, false).await.unwrap().0.completed,
+            None
+        );
+
+        // The triggers keep the timestamps right when tasks are changed directly too
+        sqlx::query(
+            "INSERT INTO tasks (description, complete, priority, notes) VALUES ('Direct', true, 0, '')",
+        )
+        .execute(db.connection())
+        .await
+        .unwrap();
+        sqlx::query("UPDATE tasks SET updated = 0, completed = NULL WHERE id = ?")
+            .bind(task.id)
+            .execute(db.connection())
+            .await
+            .unwrap();
+        sqlx::query("UPDATE tasks SET description = 'Changed', complete = true WHERE id = ?")
+            .bind(task.id)
+            .execute(db.connection())
+            .await
+            .unwrap();
+
+        let direct = db.list_tasks(true).await.unwrap().pop().unwrap();
+        assert!(direct.created.is_some() && direct.completed.is_some());
+        let task = db.get_task(task.id).await.unwrap();
+        assert_ne!(task.updated, Some(0));
+        assert!(task.completed.is_some());
+    }
+
+    #[tokio::test]
+    async fn list_tasks_with_children_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 0);
+
+        let task = db
+            .add_task(&NewTask::new("A test task", None))
+            .await
+            .unwrap();
+        db.add_task(&NewTask::new("A child task", Some(task.id)))
+            .await
+            .unwrap();
+
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 2);
+        assert_eq!(db.list_tasks(false).await.unwrap().len(), 1);
+
+        db.remove_task(task.id).await.unwrap();
+
+        // Ensure that subtasks are properly cascade-deleted
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn set_subtree_completion_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let task = db
+            .add_task(&NewTask::new("A test task", None))
+            .await
+            .unwrap();
+        let child = db
+            .add_task(&NewTask::new("A child task", Some(task.id)))
+            .await
+            .unwrap();
+        db.add_task(&NewTask::new("A grandchild task", Some(child.id)))
+            .await
+            .unwrap();
+        let other_task = db
+            .add_task(&NewTask::new("Another task", None))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.set_subtree_completion(child.id, true)
+                .await
+                .unwrap()
+                .0
+                .len(),
+            2
+        );
+        assert_eq!(
+            db.set_subtree_completion(task.id, true)
+                .await
+                .unwrap()
+                .0
+                .len(),
+            3
+        );
+        assert!(db
+            .list_tasks(true)
+            .await
+            .unwrap()
+            .iter()
+            .all(|task| task.complete == (task.id != other_task.id)));
+
+        assert!(db
+            .set_subtree_completion(100, true)
+            .await
+            .unwrap()
+            .0
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn complete_with_subtasks_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let task = db
+            .add_task(&NewTask {
+                complete_with_subtasks: Some(true),
+                ..NewTask::new("A test task", None)
+            })
+            .await
+            .unwrap();
+        let first = db
+            .add_task(&NewTask::new("A child task", Some(task.id)))
+            .await
+            .unwrap();
+        let second = db
+            .add_task(&NewTask::new("Another child task", Some(task.id)))
+            .await
+            .unwrap();
+        let grandchild = db
+            .add_task(&NewTask::new("A grandchild task", Some(second.id)))
+            .await
+            .unwrap();
+
+        let progress = db.list_progress().await.unwrap();
+        assert_eq!(progress.get(&task.id), Some(&(0, 3)));
+        assert_eq!(progress.get(&second.id), Some(&(0, 1)));
+        assert_eq!(progress.get(&first.id), None);
+
+        assert!(db
+            .set_completion(first.id, true)
+            .await
+            .unwrap()
+            .2
+            .is_empty());
+        // The second child doesn't complete with its subtasks, so the task doesn't either
+        assert!(db
+            .set_completion(grandchild.id, true)
+            .await
+            .unwrap()
+            .2
+            .is_empty());
+        assert_eq!(
+            db.list_progress().await.unwrap().get(&task.id),
+            Some(&(2, 3))
+        );
+
+        let (_, _, parents) = db.set_completion(second.id, true).await.unwrap();
+        assert_eq!(parents.len(), 1);
+        assert!(parents[0].id == task.id && parents[0].complete);
+
+        let (_, _, parents) = db.set_subtree_completion(second.id, false).await.unwrap();
+        assert_eq!(parents.len(), 1);
+        assert!(!db.get_task(task.id).await.unwrap().complete);
+
+        // Tasks can be changed to follow the config again, which leaves their parents alone
+        db.update_task(
+            task.id,
+            &TaskUpdate {
+                complete_with_subtasks: Some(None),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(db
+            .set_subtree_completion(second.id, true)
+            .await
+            .unwrap()
+            .2
+            .is_empty());
+        assert_eq!(
+            db.list_progress().await.unwrap().get(&task.id),
+            Some(&(3, 3))
+        );
+    }
+
+    #[tokio::test]
+    async fn repeat_task_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let due = Local::now().timestamp() - 60 * 60;
+        let task = db
+            .add_task(&NewTask {
+                due: Some(due),
+                tags: vec!["+review".to_owned()],
+                recurrence: Some(Recurrence::parse("every 2 days after completion").unwrap()),
+                ..NewTask::new("Weekly review", None)
+            })
+            .await
+            .unwrap();
+        let child = db
+            .add_task(&NewTask {
+                scheduled: Some(due - 60),
+                ..NewTask::new("Clear the inbox", Some(task.id))
+            })
+            .await
+            .unwrap();
+        db.set_completion(child.id, true).await.unwrap();
+
+        let (task, next, _) = db.set_completion(task.id, true).await.unwrap();
+        let next = next.unwrap();
+        // The rule moves over to the copy, so the task itself won't be copied again
+        assert_eq!(task.recurrence, None);
+        assert_eq!(
+            next.recurrence.as_deref(),
+            Some("every 2 days after completion")
+        );
+        assert!(!next.complete);
+        assert!(next.due.unwrap() > due + 24 * 60 * 60);
+        assert_eq!(db.list_tasks_with_tag("+review").await.unwrap().len(), 2);
+
+        let copied_child = db
+            .list_tasks(true)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|task| task.parent == Some(next.id))
+            .unwrap();
+        assert_eq!(copied_child.description, "Clear the inbox");
+        assert!(!copied_child.complete);
+        assert_eq!(copied_child.scheduled, Some(next.due.unwrap() - 60));
+
+        // Undoing puts back the rule and gets rid of the copies in one go
+        db.undo_or_redo(true).await.unwrap();
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 2);
+        assert!(db.get_task(task.id).await.unwrap().recurrence.is_some());
+
+        // Marking it as done again doesn't make another copy
+        db.undo_or_redo(false).await.unwrap();
+        assert!(db.set_completion(task.id, true).await.unwrap().1.is_none());
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 4);
+
+        // Tasks without dates are scheduled for next time
+        let undated = db
+            .add_task(&NewTask {
+                recurrence: Some(Recurrence::parse("daily").unwrap()),
+                ..NewTask::new("Standup prep", None)
+            })
+            .await
+            .unwrap();
+        let (_, next, _) = db.set_subtree_completion(undated.id, true).await.unwrap();
+        let next = next.unwrap();
+        assert_eq!(next.due, None);
+        assert!(next.scheduled.unwrap() > Local::now().timestamp());
+    }
+
+    #[tokio::test]
+    async fn block_task_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let first = db.add_task(&NewTask::new("First", None)).await.unwrap();
+        let second = db.add_task(&NewTask::new("Second", None)).await.unwrap();
+        let third = db
+            .add_task(&NewTask::new("Third", Some(first.id)))
+            .await
+            .unwrap();
+
+        db.block_task(second.id, first.id).await.unwrap();
+        db.block_task(third.id, second.id).await.unwrap();
+        // Blocking by the same task twice does nothing
+        db.block_task(third.id, second.id).await.unwrap();
+
+        assert!(matches!(
+            db.block_task(first.id, third.id).await,
+            Err(BlockTaskError::Cycle)
+        ));
+        assert!(matches!(
+            db.block_task(first.id, first.id).await,
+            Err(BlockTaskError::Cycle)
+        ));
+        assert!(matches!(
+            db.block_task(first.id, 100).await,
+            Err(BlockTaskError::Database(sqlx::Error::RowNotFound))
+        ));
+        // The trigger refuses cycles even if they're made directly
+        assert!(
+            sqlx::query("INSERT INTO task_dependencies (task, blocked_by) VALUES (?, ?)")
+                .bind(first.id)
+                .bind(third.id)
+                .execute(db.connection())
+                .await
+                .is_err()
+        );
+
+        let blockers = db.list_open_blockers().await.unwrap();
+        assert_eq!(blockers.get(&second.id), Some(&vec![first.id]));
+        assert_eq!(blockers.get(&third.id), Some(&vec![second.id]));
+        assert_eq!(blockers.get(&first.id), None);
+
+        // Blockers which are done don't count any more
+        db.set_completion(first.id, true).await.unwrap();
+        assert_eq!(db.list_open_blockers().await.unwrap().get(&second.id), None);
+
+        assert!(db.unblock_task(third.id, second.id).await.unwrap());
+        assert!(!db.unblock_task(third.id, second.id).await.unwrap());
+        db.undo_or_redo(true).await.unwrap();
+        assert_eq!(
+            db.list_open_blockers().await.unwrap().get(&third.id),
+            Some(&vec![second.id])
+        );
+    }
+
+    #[tokio::test]
+    async fn time_tracking_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let parent = db.add_task(&NewTask::new("Parent", None)).await.unwrap();
+        let child = db
+            .add_task(&NewTask::new("Child", Some(parent.id)))
+            .await
+            .unwrap();
+
+        db.log_time(parent.id, 1000, 600).await.unwrap();
+        db.log_time(child.id, 2000, 1800).await.unwrap();
+        assert!(matches!(
+            db.log_time(100, 0, 60).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+
+        let spent = db.list_time_spent(10_000).await.unwrap();
+        assert_eq!(spent.get(&parent.id), Some(&(600, 2400)));
+        assert_eq!(spent.get(&child.id), Some(&(1800, 1800)));
+
+        // Only the part of an entry inside the range counts
+        let between = db.list_time_between(1300, 2300, 10_000).await.unwrap();
+        assert_eq!(between.get(&parent.id), Some(&300));
+        assert_eq!(between.get(&child.id), Some(&300));
+
+        assert_eq!(db.stop_timer().await.unwrap(), None);
+        let (first_timer, stopped) = db.start_timer(parent.id).await.unwrap();
+        assert_eq!(stopped, None);
+        assert_eq!(first_timer.stopped, None);
+        // Starting the same timer again leaves it running
+        assert_eq!(
+            db.start_timer(parent.id).await.unwrap(),
+            (first_timer.clone(), None)
+        );
+
+        let (second_timer, stopped) = db.start_timer(child.id).await.unwrap();
+        assert_eq!(stopped.map(|entry| entry.id), Some(first_timer.id));
+        assert_eq!(
+            db.running_timer().await.unwrap(),
+            Some(second_timer.clone())
+        );
+
+        // Undoing puts the first timer back to running, and redoing stops it again
+        db.undo_or_redo(true).await.unwrap();
+        assert_eq!(db.running_timer().await.unwrap(), Some(first_timer));
+        db.undo_or_redo(false).await.unwrap();
+        assert_eq!(
+            db.running_timer().await.unwrap(),
+            Some(second_timer.clone())
+        );
+
+        let stopped = db.stop_timer().await.unwrap().unwrap();
+        assert_eq!(stopped.id, second_timer.id);
+        assert!(stopped.stopped.is_some());
+        assert_eq!(db.running_timer().await.unwrap(), None);
+
+        // Time spent on tasks in the trash isn't counted
+        db.remove_task(child.id).await.unwrap();
+        let spent = db.list_time_spent(10_000).await.unwrap();
+        assert_eq!(spent.get(&child.id), None);
+        let (own, with_subtasks) = spent[&parent.id];
+        assert_eq!(own, with_subtasks);
+    }
+
+    #[tokio::test]
+    async fn task_estimates_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let parent = db
+            .add_task(&NewTask {
+                estimate: Some(2 * 60 * 60),
+                ..NewTask::new("Parent", None)
+            })
+            .await
+            .unwrap();
+        let child = db
+            .add_task(&NewTask {
+                estimate: Some(30 * 60),
+                ..NewTask::new("Child", Some(parent.id))
+            })
+            .await
+            .unwrap();
+        let grandchild = db
+            .add_task(&NewTask {
+                estimate: Some(15 * 60),
+                ..NewTask::new("Grandchild", Some(child.id))
+            })
+            .await
+            .unwrap();
+        // Tasks without an estimate count as nothing
+        db.add_task(&NewTask::new("Unestimated", Some(parent.id)))
+            .await
+            .unwrap();
+
+        let task_tree = db.list_subtasks(parent.id).await.unwrap();
+        assert_eq!(task_tree.estimate, Some(2 * 60 * 60));
+        assert_eq!(task_tree.remaining_estimate(), (120 + 30 + 15) * 60);
+
+        // Work that's done isn't left any more, but a subtask of it which isn't done still is
+        db.set_completion(child.id, true).await.unwrap();
+        let task_tree = db.list_subtasks(parent.id).await.unwrap();
+        assert_eq!(task_tree.remaining_estimate(), (120 + 15) * 60);
+
+        let elements: Vec<FlatTaskTreeElement> = task_tree.into();
+        let remaining: HashMap<i64, Option<i64>> = elements
+            .iter()
+            .map(|element| (element.task.id, element.remaining_estimate))
+            .collect();
+        assert_eq!(remaining[&child.id], Some(15 * 60));
+        assert_eq!(remaining[&grandchild.id], Some(15 * 60));
+
+        let task = db
+            .update_task(
+                parent.id,
+                &TaskUpdate {
+                    estimate: Some(None),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(task.estimate, None);
+        db.undo_or_redo(true).await.unwrap();
+        assert_eq!(
+            db.get_task(parent.id).await.unwrap().estimate,
+            Some(2 * 60 * 60)
+        );
+    }
+
+    #[tokio::test]
+    async fn update_task_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let task = db
+            .add_task(&NewTask {
+                due: Some(100),
+                scheduled: Some(50),
+                ..NewTask::new("A test task", None)
+            })
+            .await
+            .unwrap();
+
+        let updated_task = db
+            .update_task(
+                task.id,
+                &TaskUpdate {
+                    description: Some("A renamed task".to_owned()),
+                    due: Some(None),
+                    priority: Some(2),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(updated_task.description, "A renamed task");
+        assert_eq!(updated_task.due, None);
+        assert_eq!(updated_task.scheduled, Some(50));
+        assert_eq!(updated_task.priority, 2);
+
+        let unchanged_task = db
+            .update_task(task.id, &TaskUpdate::default())
+            .await
+            .unwrap();
+        assert_eq!(unchanged_task.description, "A renamed task");
+
+        assert!(matches!(
+            db.update_task(100, &TaskUpdate::default()).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn move_task_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let task = db
+            .add_task(&NewTask::new("A test task", None))
+            .await
+            .unwrap();
+        let child = db
+            .add_task(&NewTask::new("A child task", Some(task.id)))
+            .await
+            .unwrap();
+        let other_task = db
+            .add_task(&NewTask::new("Another task", None))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.move_task(task.id, Some(other_task.id))
+                .await
+                .unwrap()
+                .parent,
+            Some(other_task.id)
+        );
+        assert_eq!(db.list_tasks(false).await.unwrap().len(), 1);
+        assert_eq!(
+            db.list_subtasks(other_task.id).await.unwrap().children[0].children[0].id,
+            child.id
+        );
+
+        assert!(matches!(
+            db.move_task(task.id, Some(task.id)).await,
+            Err(MoveTaskError::Cycle)
+        ));
+        assert!(matches!(
+            db.move_task(other_task.id, Some(child.id)).await,
+            Err(MoveTaskError::Cycle)
+        ));
+        assert!(matches!(
+            db.move_task(task.id, Some(100)).await,
+            Err(MoveTaskError::Database(_))
+        ));
+
+        assert_eq!(db.move_task(child.id, None).await.unwrap().parent, None);
+        assert_eq!(db.list_tasks(false).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn disallow_parent_cycles_in_database_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let task = db
+            .add_task(&NewTask::new("A test task", None))
+            .await
+            .unwrap();
+        let child = db
+            .add_task(&NewTask::new("A child task", Some(task.id)))
+            .await
+            .unwrap();
+        let grandchild = db
+            .add_task(&NewTask::new("A grandchild task", Some(child.id)))
+            .await
+            .unwrap();
+
+        // Going around move_task to make sure the trigger catches cycles by itself
+        for new_parent in [task.id, grandchild.id] {
+            assert!(sqlx::query("UPDATE tasks SET parent = ? WHERE id = ?")
+                .bind(new_parent)
+                .bind(task.id)
+                .execute(db.connection())
+                .await
+                .is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn set_collapsed_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let task = db
+            .add_task(&NewTask::new("A test task", None))
+            .await
+            .unwrap();
+
+        db.set_collapsed(task.id, true).await.unwrap();
+        db.set_collapsed(task.id, true).await.unwrap();
+        assert_eq!(
+            db.list_collapsed_tasks().await.unwrap(),
+            HashSet::from([task.id])
+        );
+
+        db.set_collapsed(task.id, false).await.unwrap();
+        assert!(db.list_collapsed_tasks().await.unwrap().is_empty());
+
+        db.set_collapsed(task.id, true).await.unwrap();
+        db.remove_task(task.id).await.unwrap();
+        assert!(db.list_collapsed_tasks().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn undo_and_redo_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        assert_eq!(db.undo_or_redo(true).await.unwrap(), None);
+
+        let parent = db
+            .add_task(&NewTask::new("A parent task", None))
+            .await
+            .unwrap();
+        let child = db
+            .add_task(&NewTask {
+                tags: vec!["@home".to_owned()],
+                ..NewTask::new("A child task", Some(parent.id))
+            })
+            .await
+            .unwrap();
+        db.set_completion(child.id, true).await.unwrap();
+        let before_removal = db.list_tasks(true).await.unwrap();
+
+        // Removing a task and undoing it should bring back its subtasks and their tags too
+        db.remove_task(parent.id).await.unwrap();
+        assert_eq!(
+            db.undo_or_redo(true).await.unwrap(),
+            Some(format!("remove task {}", parent.id))
+        );
+        assert_eq!(
+            format!("{:?}", db.list_tasks(true).await.unwrap()),
+            format!("{:?}", before_removal)
+        );
+        assert_eq!(db.list_tasks_with_tag("@home").await.unwrap().len(), 1);
+
+        assert!(db.undo_or_redo(false).await.unwrap().is_some());
+        assert!(db.list_tasks(true).await.unwrap().is_empty());
+        assert_eq!(db.undo_or_redo(false).await.unwrap(), None);
+        db.undo_or_redo(true).await.unwrap();
+
+        // Changes made together are undone together
+        db.begin_change("edit task").await.unwrap();
+        db.update_task(
+            child.id,
+            &TaskUpdate {
+                description: Some("An edited task".to_owned()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        db.tag_task(child.id, "+urgent").await.unwrap();
+        db.end_change().await.unwrap();
+
+        assert_eq!(
+            db.undo_or_redo(true).await.unwrap(),
+            Some("edit task".to_owned())
+        );
+        assert_eq!(
+            db.get_task(child.id).await.unwrap().description,
+            "A child task"
+        );
+        assert!(db.list_tasks_with_tag("+urgent").await.unwrap().is_empty());
+
+        // Making a new change means what was undone can't be redone any more
+        db.set_completion(parent.id, true).await.unwrap();
+        assert_eq!(db.undo_or_redo(false).await.unwrap(), None);
+        assert_eq!(
+            db.undo_or_redo(true).await.unwrap(),
+            Some(format!("complete task {}", parent.id))
+        );
+        assert!(!db.get_task(parent.id).await.unwrap().complete);
+    }
+
+    #[tokio::test]
+    async fn tag_task_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let task = db
+            .add_task(&NewTask {
+                tags: vec!["@work".to_owned(), "+release".to_owned()],
+                ..NewTask::new("A test task", None)
+            })
+            .await
+            .unwrap();
+        db.add_task(&NewTask::new("An untagged task", None))
+            .await
+            .unwrap();
+
+        assert_eq!(db.list_tasks_with_tag("@work").await.unwrap().len(), 1);
+        assert_eq!(db.list_tasks_with_tag("+work").await.unwrap().len(), 0);
+
+        db.tag_task(task.id, "@work").await.unwrap();
+        assert!(db.untag_task(task.id, "+release").await.unwrap());
+        assert!(!db.untag_task(task.id, "+release").await.unwrap());
+        assert_eq!(
+            db.list_task_tags().await.unwrap().get(&task.id),
+            Some(&vec!["@work".to_owned()])
+        );
+
+        db.remove_task(task.id).await.unwrap();
+
+        // Removing a task should remove its tags too
+        assert_eq!(db.list_tasks_with_tag("@work").await.unwrap().len(), 0);
+        assert!(db.list_task_tags().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    #[should_panic]
+    /// This is a regression test, previously this was the single way to add a cycle to the 'tree'.
+    /// As that's now patched we should never be able to get cycles so long as the user doesn't
     /// manually edit the database (e.g. with an SQL client). Even so, they would not be able to
     /// add a task that refers to itself as that is protected by a trigger in the SQLite database
     /// itself.
@@ -488,6 +3117,8 @@ mod tests {
 
         assert_eq!(db.list_tasks(true).await.unwrap().len(), 0);
 
-        db.add_task("A test task", Some(1)).await.unwrap();
+        db.add_task(&NewTask::new("A test task", Some(1)))
+            .await
+            .unwrap();
     }
 }
@@ -0,0 +1,234 @@
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Weekday,
+};
+
+use crate::config;
+
+const WEEKDAYS: [(&str, &str, Weekday); 7] = [
+    ("monday", "mon", Weekday::Mon),
+    ("tuesday", "tue", Weekday::Tue),
+    ("wednesday", "wed", Weekday::Wed),
+    ("thursday", "thu", Weekday::Thu),
+    ("friday", "fri", Weekday::Fri),
+    ("saturday", "sat", Weekday::Sat),
+    ("sunday", "sun", Weekday::Sun),
+];
+
+pub fn parse_weekday(text: &str) -> Option<Weekday> {
+    WEEKDAYS
+        .iter()
+        .find(|(name, short_name, _)| text == *name || text == *short_name)
+        .map(|(_, _, weekday)| *weekday)
+}
+
+/// How many days it is from `today` until the next time it is `weekday`, which is 0 if it's
+/// `weekday` today
+fn days_until(weekday: Weekday, today: NaiveDate) -> i64 {
+    (weekday.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64)
+        .rem_euclid(7)
+}
+
+/// Parses just the day part of a date, like `tomorrow`, `+3d`, `-1w`, `next friday` or `2023-01-31`
+fn parse_day(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match text {
+        "today" => return Some(today),
+        "tomorrow" => return today.succ_opt(),
+        "yesterday" => return today.pred_opt(),
+        _ => {}
+    }
+
+    // Offsets can go back in time too, like `-1w`, which is handy for looking back at what was done
+    let offset = match text.strip_prefix('+') {
+        Some(offset) => Some((offset, false)),
+        None => text.strip_prefix('-').map(|offset| (offset, true)),
+    };
+    if let Some((offset, backwards)) = offset {
+        let unit_start = offset.find(|char: char| !char.is_ascii_digit())?;
+        let (amount, unit) = offset.split_at(unit_start);
+        let amount = amount.parse::<i64>().ok()?;
+        let months = |months: i64| {
+            let months = chrono::Months::new(months.try_into().ok()?);
+            if backwards {
+                today.checked_sub_months(months)
+            } else {
+                today.checked_add_months(months)
+            }
+        };
+        let signed_amount = if backwards { -amount } else { amount };
+
+        return match unit {
+            "d" | "day" | "days" => today.checked_add_signed(Duration::days(signed_amount)),
+            "w" | "week" | "weeks" => today.checked_add_signed(Duration::weeks(signed_amount)),
+            "m" | "month" | "months" => months(amount),
+            "y" | "year" | "years" => months(amount * 12),
+            _ => None,
+        };
+    }
+
+    if let Some(weekday) = text.strip_prefix("next ").and_then(parse_weekday) {
+        // "next friday" is never today, even if today is a Friday
+        return today.checked_add_signed(Duration::days(match days_until(weekday, today) {
+            0 => 7,
+            days => days,
+        }));
+    }
+
+    if let Some(weekday) = parse_weekday(text) {
+        return today.checked_add_signed(Duration::days(days_until(weekday, today)));
+    }
+
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()
+}
+
+/// Parses a date that a user has typed, returning it as a unix timestamp
+///
+/// Dates can either be absolute (`2023-01-31`) or relative to `now` (`today`, `tomorrow`, `+3d`,
+/// `+2w`, `+1m`, `-1w`, `friday`, `next friday`), and can optionally be followed by a 24-hour time
+/// (`tomorrow 17:30`). Without a time, the date is taken to mean the start of that day
+pub fn parse_date(text: &str, now: DateTime<Local>) -> Result<i64, String> {
+    let text = text.trim().to_lowercase();
+
+    let (day_text, time) = match text.rsplit_once(' ') {
+        Some((day_text, time_text)) => match NaiveTime::parse_from_str(time_text, "%H:%M") {
+            Ok(time) => (day_text, time),
+            Err(_) => (text.as_str(), NaiveTime::MIN),
+        },
+        None => (text.as_str(), NaiveTime::MIN),
+    };
+
+    let day = parse_day(day_text, now.date_naive())
+        .ok_or_else(|| format!("'{}' isn't a date TeaL understands", text))?;
+
+    Local
+        .from_local_datetime(&day.and_time(time))
+        .earliest()
+        .map(|date| date.timestamp())
+        .ok_or_else(|| format!("'{}' doesn't exist in your timezone", text))
+}
+
+pub fn to_local(timestamp: i64) -> DateTime<Local> {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .earliest()
+        .unwrap_or_else(Local::now)
+}
+
+/// Checks that a format for [`format_date`] is a valid strftime format, as formatting with an invalid
+/// one panics
+pub fn check_format(format: &str) -> Result<(), String> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        Err(format!("Invalid date format: {}", format))
+    } else {
+        Ok(())
+    }
+}
+
+/// Formats a timestamp for display with the formats from the config, leaving off the time if it's at
+/// the very start of the day
+pub fn format_date(timestamp: i64) -> String {
+    let date = to_local(timestamp);
+
+    let formats = &config::get().dates;
+
+    if date.time() == NaiveTime::MIN {
+        date.format(&formats.format).to_string()
+    } else {
+        date.format(&formats.format_with_time).to_string()
+    }
+}
+
+/// Whether something due at the given timestamp is overdue. Dates without a time are due by the end
+/// of their day, so they are only overdue from the day after
+pub fn is_overdue(due: i64, now: DateTime<Local>) -> bool {
+    let due = to_local(due);
+
+    if due.time() == NaiveTime::MIN {
+        due.date_naive() < now.date_naive()
+    } else {
+        due < now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_relative_dates_test() {
+        // 2023-01-04 is a Wednesday
+        let now = at(2023, 1, 4, 12, 0);
+
+        assert_eq!(
+            parse_date("today", now),
+            Ok(at(2023, 1, 4, 0, 0).timestamp())
+        );
+        assert_eq!(
+            parse_date("Tomorrow", now),
+            Ok(at(2023, 1, 5, 0, 0).timestamp())
+        );
+        assert_eq!(parse_date("+3d", now), Ok(at(2023, 1, 7, 0, 0).timestamp()));
+        assert_eq!(
+            parse_date("+2w", now),
+            Ok(at(2023, 1, 18, 0, 0).timestamp())
+        );
+        assert_eq!(parse_date("+1m", now), Ok(at(2023, 2, 4, 0, 0).timestamp()));
+        assert_eq!(
+            parse_date("-1w", now),
+            Ok(at(2022, 12, 28, 0, 0).timestamp())
+        );
+        assert_eq!(
+            parse_date("-2m", now),
+            Ok(at(2022, 11, 4, 0, 0).timestamp())
+        );
+        assert_eq!(
+            parse_date("friday", now),
+            Ok(at(2023, 1, 6, 0, 0).timestamp())
+        );
+        assert_eq!(parse_date("wed", now), Ok(at(2023, 1, 4, 0, 0).timestamp()));
+        assert_eq!(
+            parse_date("next wednesday", now),
+            Ok(at(2023, 1, 11, 0, 0).timestamp())
+        );
+        assert_eq!(
+            parse_date("next friday", now),
+            Ok(at(2023, 1, 6, 0, 0).timestamp())
+        );
+    }
+
+    #[test]
+    fn parse_absolute_dates_test() {
+        let now = at(2023, 1, 4, 12, 0);
+
+        assert_eq!(
+            parse_date("2023-03-01", now),
+            Ok(at(2023, 3, 1, 0, 0).timestamp())
+        );
+        assert_eq!(
+            parse_date("2023-03-01 17:30", now),
+            Ok(at(2023, 3, 1, 17, 30).timestamp())
+        );
+        assert_eq!(
+            parse_date("tomorrow 09:15", now),
+            Ok(at(2023, 1, 5, 9, 15).timestamp())
+        );
+        assert!(parse_date("the day after never", now).is_err());
+        assert!(parse_date("+3 fortnights", now).is_err());
+    }
+
+    #[test]
+    fn overdue_test() {
+        let now = at(2023, 1, 4, 12, 0);
+
+        assert!(!is_overdue(at(2023, 1, 4, 0, 0).timestamp(), now));
+        assert!(is_overdue(at(2023, 1, 3, 0, 0).timestamp(), now));
+        assert!(is_overdue(at(2023, 1, 4, 11, 0).timestamp(), now));
+        assert!(!is_overdue(at(2023, 1, 4, 13, 0).timestamp(), now));
+    }
+}
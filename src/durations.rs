@@ -0,0 +1,88 @@
+/// How many seconds each unit a length of time can be written in is
+const UNITS: [(&str, i64); 3] = [("d", 24 * 60 * 60), ("h", 60 * 60), ("m", 60)];
+
+/// Parses a length of time that a user has typed, like `2h`, `30m`, `1h30m` or `1d 4h`, returning it
+/// in seconds. A number on its own is taken to be minutes, and hours can have a fraction like `1.5h`
+pub fn parse_duration(text: &str) -> Result<i64, String> {
+    let text = text.trim().to_lowercase();
+    let invalid = || {
+        format!(
+            "'{}' isn't a length of time TeaL understands, try something like 2h, 30m or 1h30m",
+            text
+        )
+    };
+
+    let compact: String = text.chars().filter(|char| !char.is_whitespace()).collect();
+    if let Ok(minutes) = compact.parse::<i64>() {
+        return if minutes > 0 {
+            Ok(minutes * 60)
+        } else {
+            Err(invalid())
+        };
+    }
+
+    let mut seconds = 0.0;
+    let mut rest = compact.as_str();
+    while !rest.is_empty() {
+        let unit_start = rest
+            .find(|char: char| !char.is_ascii_digit() && char != '.')
+            .ok_or_else(invalid)?;
+        let (amount, unit_and_rest) = rest.split_at(unit_start);
+        let amount = amount.parse::<f64>().map_err(|_| invalid())?;
+
+        let (unit, unit_seconds) = UNITS
+            .iter()
+            .find(|(unit, _)| unit_and_rest.starts_with(unit))
+            .ok_or_else(invalid)?;
+        seconds += amount * *unit_seconds as f64;
+        rest = &unit_and_rest[unit.len()..];
+    }
+
+    match seconds.round() as i64 {
+        seconds if seconds > 0 => Ok(seconds),
+        _ => Err(invalid()),
+    }
+}
+
+/// Formats a length of time in seconds to be shown, like `1h 30m`. Anything under a minute is left
+/// off, and it's never written in days, as days of work are hard to tell apart from days on the
+/// calendar
+pub fn format_duration(seconds: i64) -> String {
+    let minutes = seconds.max(0) / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_test() {
+        assert_eq!(parse_duration("2h"), Ok(2 * 60 * 60));
+        assert_eq!(parse_duration("30m"), Ok(30 * 60));
+        assert_eq!(parse_duration("1h30m"), Ok(90 * 60));
+        assert_eq!(parse_duration("1H 30M"), Ok(90 * 60));
+        assert_eq!(parse_duration("1.5h"), Ok(90 * 60));
+        assert_eq!(parse_duration("1d 4h"), Ok(28 * 60 * 60));
+        assert_eq!(parse_duration("45"), Ok(45 * 60));
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("0m").is_err());
+        assert!(parse_duration("2 fortnights").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn format_duration_test() {
+        assert_eq!(format_duration(0), "0m");
+        assert_eq!(format_duration(59), "0m");
+        assert_eq!(format_duration(45 * 60), "45m");
+        assert_eq!(format_duration(2 * 60 * 60), "2h");
+        assert_eq!(format_duration(90 * 60 + 20), "1h 30m");
+        assert_eq!(format_duration(28 * 60 * 60), "28h");
+    }
+}
@@ -0,0 +1,47 @@
+use std::{env, fs, process::Command};
+
+use color_eyre::Report;
+
+/// Lets the user edit some text in their preferred editor, returning what they saved
+///
+/// The editor is taken from `$VISUAL` or `$EDITOR`, falling back to `vi`. These can include
+/// arguments, for example `code --wait`. The text is written to a temporary file which the editor
+/// is opened on, and the file is removed again afterwards
+pub fn edit_text(text: &str, file_name: &str) -> eyre::Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_owned());
+    let mut editor_words = editor.split_whitespace();
+    let editor_command = editor_words
+        .next()
+        .ok_or_else(|| Report::msg("Your $VISUAL or $EDITOR is empty"))?;
+
+    let path = env::temp_dir().join(format!("TeaL-{}-{}", std::process::id(), file_name));
+    // Editors expect files to end in a newline, and we trim it off again afterwards
+    fs::write(
+        &path,
+        if text.is_empty() {
+            "".to_owned()
+        } else {
+            format!("{}\n", text)
+        },
+    )?;
+
+    let status = Command::new(editor_command)
+        .args(editor_words)
+        .arg(&path)
+        .status();
+
+    let edited_text = fs::read_to_string(&path);
+    fs::remove_file(&path)?;
+
+    let status = status?;
+    if !status.success() {
+        return Err(Report::msg(format!(
+            "{} exited unsuccessfully ({}), so nothing was changed",
+            editor_command, status
+        )));
+    }
+
+    Ok(edited_text?.trim_end().to_owned())
+}
@@ -0,0 +1,779 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Something that can be done by pressing a key in the TUI
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    AddTask,
+    AddSubtask,
+    SelectNext,
+    SelectPrevious,
+    Collapse,
+    Expand,
+    Edit,
+    EditNotes,
+    Move,
+    MoveToTop,
+    Remove,
+    ToggleCompletion,
+    CycleSort,
+    Undo,
+    Redo,
+    Search,
+    SwitchList,
+    Open,
+    GoUp,
+    ScrollNotesDown,
+    ScrollNotesUp,
+    Submit,
+    DeleteCharacter,
+    ClearText,
+    Cancel,
+    ShowHelp,
+    Back,
+    Quit,
+}
+
+/// The parts of the TUI which each have their own keys
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Context {
+    TaskList,
+    TaskFullscreen,
+    MovePicker,
+    ListPicker,
+    /// Any box that text is typed into, including the search bar
+    TextInput,
+}
+
+/// A single key press, along with any modifier keys that were held down with it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+/// One or more key presses which have to be made one after another, like `g g`
+pub type Chord = Vec<KeyPress>;
+
+const NAMED_KEYS: [(&str, KeyCode); 14] = [
+    ("space", KeyCode::Char(' ')),
+    ("enter", KeyCode::Enter),
+    ("escape", KeyCode::Esc),
+    ("backspace", KeyCode::Backspace),
+    ("tab", KeyCode::Tab),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("delete", KeyCode::Delete),
+];
+
+impl From<KeyEvent> for KeyPress {
+    fn from(event: KeyEvent) -> Self {
+        // Shift is already part of which character was typed, so `N` doesn't need to be written as
+        // `shift+n`
+        let modifiers = match event.code {
+            KeyCode::Char(_) => event.modifiers - KeyModifiers::SHIFT,
+            _ => event.modifiers,
+        };
+
+        KeyPress {
+            code: event.code,
+            modifiers: modifiers
+                & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT),
+        }
+    }
+}
+
+impl KeyPress {
+    /// Parses a key as it's written in the config file, like `j`, `N`, `enter`, `ctrl+r` or `f5`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = text;
+
+        // `+` on its own, or at the end like `ctrl++`, is the plus key rather than a separator
+        while let Some((modifier, rest)) = key.split_once('+').filter(|(_, rest)| !rest.is_empty())
+        {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("'{}' isn't a modifier key in '{}'", modifier, text)),
+            };
+            key = rest;
+        }
+
+        let mut characters = key.chars();
+        let code = match (characters.next(), characters.next()) {
+            (Some(character), None) => KeyCode::Char(character),
+            _ => {
+                let lowercase_key = key.to_lowercase();
+                match NAMED_KEYS.iter().find(|(name, _)| *name == lowercase_key) {
+                    Some((_, code)) => *code,
+                    None if lowercase_key == "esc" => KeyCode::Esc,
+                    None => match lowercase_key
+                        .strip_prefix('f')
+                        .and_then(|number| number.parse::<u8>().ok())
+                    {
+                        Some(number) if (1..=12).contains(&number) => KeyCode::F(number),
+                        _ => return Err(format!("'{}' isn't a key TeaL knows about", text)),
+                    },
+                }
+            }
+        };
+
+        Ok(match code {
+            KeyCode::Char(character) if modifiers.contains(KeyModifiers::SHIFT) => KeyPress {
+                code: KeyCode::Char(character.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            code => KeyPress { code, modifiers },
+        })
+    }
+
+    /// The name of the key as it's shown in the TUI, like `j`, `<ENTER>` or `<CTRL+r>`
+    pub fn name(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(character) if character != ' ' => character.to_string(),
+            KeyCode::F(number) => format!("F{}", number),
+            code => NAMED_KEYS
+                .iter()
+                .find(|(_, named_code)| *named_code == code)
+                .map(|(name, _)| name.to_uppercase())
+                .unwrap_or_else(|| format!("{:?}", code)),
+        };
+
+        let mut modifiers = vec![];
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            modifiers.push("CTRL");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            modifiers.push("ALT");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            modifiers.push("SHIFT");
+        }
+
+        if modifiers.is_empty() && matches!(self.code, KeyCode::Char(character) if character != ' ')
+        {
+            key
+        } else {
+            modifiers.push(&key);
+            format!("<{}>", modifiers.join("+"))
+        }
+    }
+}
+
+/// Parses a chord as it's written in the config file, with the keys separated by spaces, like `g g`
+pub fn parse_chord(text: &str) -> Result<Chord, String> {
+    let chord = text
+        .split_whitespace()
+        .map(KeyPress::parse)
+        .collect::<Result<Chord, String>>()?;
+
+    if chord.is_empty() {
+        return Err("Keys can't be empty".to_owned());
+    }
+
+    Ok(chord)
+}
+
+pub fn chord_name(chord: &[KeyPress]) -> String {
+    chord
+        .iter()
+        .map(KeyPress::name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+struct DefaultBinding {
+    action: Action,
+    /// What the action is called in the config file
+    name: &'static str,
+    keys: &'static [&'static str],
+    /// What the action does, shown in the help overlay
+    description: &'static str,
+}
+
+const TASK_LIST: &[DefaultBinding] = &[
+    DefaultBinding {
+        action: Action::SelectNext,
+        name: "select_next",
+        keys: &["j", "down"],
+        description: "Select the next task",
+    },
+    DefaultBinding {
+        action: Action::SelectPrevious,
+        name: "select_previous",
+        keys: &["k", "up"],
+        description: "Select the previous task",
+    },
+    DefaultBinding {
+        action: Action::Collapse,
+        name: "collapse",
+        keys: &["h", "left"],
+        description: "Hide the selected task's subtasks, or select its parent",
+    },
+    DefaultBinding {
+        action: Action::Expand,
+        name: "expand",
+        keys: &["l", "right"],
+        description: "Show the selected task's subtasks",
+    },
+    DefaultBinding {
+        action: Action::Open,
+        name: "open",
+        keys: &["enter"],
+        description: "Open the selected task",
+    },
+    DefaultBinding {
+        action: Action::AddTask,
+        name: "add_task",
+        keys: &["n"],
+        description: "Add a new task",
+    },
+    DefaultBinding {
+        action: Action::AddSubtask,
+        name: "add_subtask",
+        keys: &["a"],
+        description: "Add a subtask to the selected task",
+    },
+    DefaultBinding {
+        action: Action::Edit,
+        name: "edit",
+        keys: &["e"],
+        description: "Edit the selected task",
+    },
+    DefaultBinding {
+        action: Action::Move,
+        name: "move",
+        keys: &["m"],
+        description: "Move the selected task under a different parent",
+    },
+    DefaultBinding {
+        action: Action::Remove,
+        name: "remove",
+        keys: &["d"],
+        description: "Remove the selected task and its subtasks",
+    },
+    DefaultBinding {
+        action: Action::Undo,
+        name: "undo",
+        keys: &["u"],
+        description: "Undo the last change",
+    },
+    DefaultBinding {
+        action: Action::Redo,
+        name: "redo",
+        keys: &["ctrl+r"],
+        description: "Redo the last change that was undone",
+    },
+    DefaultBinding {
+        action: Action::CycleSort,
+        name: "cycle_sort",
+        keys: &["s"],
+        description: "Change how tasks are sorted",
+    },
+    DefaultBinding {
+        action: Action::Search,
+        name: "search",
+        keys: &["/"],
+        description: "Search your tasks",
+    },
+    DefaultBinding {
+        action: Action::SwitchList,
+        name: "switch_list",
+        keys: &["L"],
+        description: "Switch to another task list",
+    },
+    DefaultBinding {
+        action: Action::ShowHelp,
+        name: "show_help",
+        keys: &["?"],
+        description: "Show these keys",
+    },
+    DefaultBinding {
+        action: Action::Quit,
+        name: "quit",
+        keys: &["q"],
+        description: "Quit TeaL",
+    },
+];
+
+const TASK_FULLSCREEN: &[DefaultBinding] = &[
+    DefaultBinding {
+        action: Action::SelectNext,
+        name: "select_next",
+        keys: &["j", "down"],
+        description: "Select the next subtask",
+    },
+    DefaultBinding {
+        action: Action::SelectPrevious,
+        name: "select_previous",
+        keys: &["k", "up"],
+        description: "Select the previous subtask",
+    },
+    DefaultBinding {
+        action: Action::Open,
+        name: "open",
+        keys: &["enter"],
+        description: "Open the selected subtask",
+    },
+    DefaultBinding {
+        action: Action::GoUp,
+        name: "go_up",
+        keys: &["backspace"],
+        description: "Go up to this task's parent",
+    },
+    DefaultBinding {
+        action: Action::ToggleCompletion,
+        name: "toggle_completion",
+        keys: &["space"],
+        description: "Mark the selected subtask, or this task, as done or not done",
+    },
+    DefaultBinding {
+        action: Action::AddSubtask,
+        name: "add_subtask",
+        keys: &["n"],
+        description: "Add a subtask to the selected subtask, or this task",
+    },
+    DefaultBinding {
+        action: Action::Edit,
+        name: "edit",
+        keys: &["e"],
+        description: "Edit the selected subtask, or this task",
+    },
+    DefaultBinding {
+        action: Action::EditNotes,
+        name: "edit_notes",
+        keys: &["N"],
+        description: "Edit this task's notes in your editor",
+    },
+    DefaultBinding {
+        action: Action::Undo,
+        name: "undo",
+        keys: &["u"],
+        description: "Undo the last change",
+    },
+    DefaultBinding {
+        action: Action::Redo,
+        name: "redo",
+        keys: &["ctrl+r"],
+        description: "Redo the last change that was undone",
+    },
+    DefaultBinding {
+        action: Action::ScrollNotesDown,
+        name: "scroll_notes_down",
+        keys: &["pagedown"],
+        description: "Scroll the notes down",
+    },
+    DefaultBinding {
+        action: Action::ScrollNotesUp,
+        name: "scroll_notes_up",
+        keys: &["pageup"],
+        description: "Scroll the notes up",
+    },
+    DefaultBinding {
+        action: Action::ShowHelp,
+        name: "show_help",
+        keys: &["?"],
+        description: "Show these keys",
+    },
+    DefaultBinding {
+        action: Action::Back,
+        name: "back",
+        keys: &["q"],
+        description: "Go back to the task list",
+    },
+];
+
+const MOVE_PICKER: &[DefaultBinding] = &[
+    DefaultBinding {
+        action: Action::SelectNext,
+        name: "select_next",
+        keys: &["j", "down"],
+        description: "Select the next task",
+    },
+    DefaultBinding {
+        action: Action::SelectPrevious,
+        name: "select_previous",
+        keys: &["k", "up"],
+        description: "Select the previous task",
+    },
+    DefaultBinding {
+        action: Action::Move,
+        name: "move",
+        keys: &["enter"],
+        description: "Move the task under the selected task",
+    },
+    DefaultBinding {
+        action: Action::MoveToTop,
+        name: "move_to_top",
+        keys: &["t"],
+        description: "Move the task to the top level",
+    },
+    DefaultBinding {
+        action: Action::ShowHelp,
+        name: "show_help",
+        keys: &["?"],
+        description: "Show these keys",
+    },
+    DefaultBinding {
+        action: Action::Cancel,
+        name: "cancel",
+        keys: &["escape"],
+        description: "Leave the task where it is",
+    },
+];
+
+const LIST_PICKER: &[DefaultBinding] = &[
+    DefaultBinding {
+        action: Action::SelectNext,
+        name: "select_next",
+        keys: &["j", "down"],
+        description: "Select the next list",
+    },
+    DefaultBinding {
+        action: Action::SelectPrevious,
+        name: "select_previous",
+        keys: &["k", "up"],
+        description: "Select the previous list",
+    },
+    DefaultBinding {
+        action: Action::Open,
+        name: "open",
+        keys: &["enter"],
+        description: "Switch to the selected list",
+    },
+    DefaultBinding {
+        action: Action::Cancel,
+        name: "cancel",
+        keys: &["escape", "q"],
+        description: "Stay on the current list",
+    },
+];
+
+const TEXT_INPUT: &[DefaultBinding] = &[
+    DefaultBinding {
+        action: Action::Submit,
+        name: "submit",
+        keys: &["enter"],
+        description: "Finish typing",
+    },
+    DefaultBinding {
+        action: Action::DeleteCharacter,
+        name: "delete_character",
+        keys: &["backspace"],
+        description: "Delete the last character",
+    },
+    DefaultBinding {
+        action: Action::ClearText,
+        name: "clear_text",
+        keys: &["ctrl+u"],
+        description: "Delete everything that's been typed",
+    },
+    DefaultBinding {
+        action: Action::Cancel,
+        name: "cancel",
+        keys: &["escape"],
+        description: "Stop typing without changing anything",
+    },
+];
+
+/// Each context, what it's called in the config file, and its default keys
+const CONTEXTS: [(Context, &str, &[DefaultBinding]); 5] = [
+    (Context::TaskList, "task_list", TASK_LIST),
+    (Context::TaskFullscreen, "task_fullscreen", TASK_FULLSCREEN),
+    (Context::MovePicker, "move_picker", MOVE_PICKER),
+    (Context::ListPicker, "list_picker", LIST_PICKER),
+    (Context::TextInput, "text_input", TEXT_INPUT),
+];
+
+pub struct Binding {
+    pub action: Action,
+    pub chords: Vec<Chord>,
+    pub description: &'static str,
+}
+
+/// What a key press did, after looking it up in a [`Keymap`]
+#[derive(PartialEq, Eq, Debug)]
+pub enum Input {
+    Action(Action),
+    /// The key was the start of a chord, so more keys need to be pressed to finish it
+    Pending,
+    /// The keys don't do anything. Text inputs type these instead
+    Unbound(Chord),
+}
+
+/// Which keys do what in each part of the TUI. All key presses in the TUI are looked up here, so
+/// that any of them can be changed in the config file
+pub struct Keymap {
+    contexts: HashMap<Context, Vec<Binding>>,
+}
+
+impl Keymap {
+    /// Makes a keymap from the default keys, replacing the keys for any actions in `overrides`. The
+    /// overrides are keyed by the context's name and then the action's name, as they are written in
+    /// the config file
+    pub fn new(
+        overrides: &BTreeMap<String, BTreeMap<String, Vec<String>>>,
+    ) -> Result<Self, String> {
+        for context_name in overrides.keys() {
+            if !CONTEXTS.iter().any(|(_, name, _)| name == context_name) {
+                return Err(format!(
+                    "There are no keys called [keys.{}], expected one of {}",
+                    context_name,
+                    CONTEXTS.map(|(_, name, _)| name).join(", ")
+                ));
+            }
+        }
+
+        let mut contexts = HashMap::new();
+        for (context, context_name, defaults) in CONTEXTS {
+            let context_overrides = overrides.get(context_name);
+
+            for action_name in context_overrides.into_iter().flat_map(BTreeMap::keys) {
+                if !defaults.iter().any(|binding| binding.name == action_name) {
+                    return Err(format!(
+                        "There's no {} action in [keys.{}], expected one of {}",
+                        action_name,
+                        context_name,
+                        defaults
+                            .iter()
+                            .map(|binding| binding.name)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
+            }
+
+            let mut bindings = vec![];
+            for default in defaults {
+                let keys = match context_overrides.and_then(|keys| keys.get(default.name)) {
+                    Some(keys) => keys.iter().map(String::as_str).collect(),
+                    None => default.keys.to_vec(),
+                };
+
+                bindings.push(Binding {
+                    action: default.action,
+                    chords: keys
+                        .into_iter()
+                        .map(parse_chord)
+                        .collect::<Result<_, _>>()
+                        .map_err(|error| {
+                            format!(
+                                "{} (for {} in [keys.{}])",
+                                error, default.name, context_name
+                            )
+                        })?,
+                    description: default.description,
+                });
+            }
+            contexts.insert(context, bindings);
+        }
+
+        Ok(Keymap { contexts })
+    }
+
+    /// Every context's keys as they'd be written in the config file, with `overrides` replacing the
+    /// default keys for any actions they have keys for
+    pub fn merged_keys(
+        overrides: &BTreeMap<String, BTreeMap<String, Vec<String>>>,
+    ) -> BTreeMap<String, BTreeMap<String, Vec<String>>> {
+        CONTEXTS
+            .iter()
+            .map(|(_, context_name, defaults)| {
+                let context_overrides = overrides.get(*context_name);
+                let keys = defaults
+                    .iter()
+                    .map(|default| {
+                        let keys = match context_overrides.and_then(|keys| keys.get(default.name)) {
+                            Some(keys) => keys.clone(),
+                            None => default.keys.iter().map(|key| (*key).to_owned()).collect(),
+                        };
+                        (default.name.to_owned(), keys)
+                    })
+                    .collect();
+                ((*context_name).to_owned(), keys)
+            })
+            .collect()
+    }
+
+    pub fn bindings(&self, context: Context) -> &[Binding] {
+        &self.contexts[&context]
+    }
+
+    /// How to refer to the keys for an action in a message, like `'q'` or `<ENTER>`. Only the first
+    /// of the action's keys is used
+    pub fn key_for(&self, context: Context, action: Action) -> String {
+        let chord = self
+            .bindings(context)
+            .iter()
+            .find(|binding| binding.action == action)
+            .and_then(|binding| binding.chords.first());
+
+        match chord {
+            Some(chord) => match chord_name(chord) {
+                name if name.starts_with('<') => name,
+                name => format!("'{}'", name),
+            },
+            None => "(no key)".to_owned(),
+        }
+    }
+
+    /// Looks up a key press, along with any keys before it which are the start of a chord. Those
+    /// keys are kept in `pending` between calls, and it's emptied again once the chord is finished
+    /// or turns out not to be a chord at all
+    pub fn feed(&self, context: Context, pending: &mut Chord, key: KeyPress) -> Input {
+        pending.push(key);
+
+        let bindings = self.bindings(context);
+        if let Some(binding) = bindings
+            .iter()
+            .find(|binding| binding.chords.iter().any(|chord| chord == pending))
+        {
+            pending.clear();
+            return Input::Action(binding.action);
+        }
+
+        let is_start_of_chord = bindings
+            .iter()
+            .flat_map(|binding| &binding.chords)
+            .any(|chord| chord.starts_with(pending));
+        if is_start_of_chord {
+            Input::Pending
+        } else {
+            Input::Unbound(std::mem::take(pending))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode) -> KeyPress {
+        KeyPress {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn parse_keys_test() {
+        assert_eq!(KeyPress::parse("j"), Ok(press(KeyCode::Char('j'))));
+        assert_eq!(KeyPress::parse("Enter"), Ok(press(KeyCode::Enter)));
+        assert_eq!(KeyPress::parse("+"), Ok(press(KeyCode::Char('+'))));
+        assert_eq!(
+            KeyPress::parse("ctrl+r"),
+            Ok(KeyPress {
+                code: KeyCode::Char('r'),
+                modifiers: KeyModifiers::CONTROL
+            })
+        );
+        assert_eq!(KeyPress::parse("shift+n"), Ok(press(KeyCode::Char('N'))));
+        assert_eq!(KeyPress::parse("f5"), Ok(press(KeyCode::F(5))));
+        assert!(KeyPress::parse("hyper+x").is_err());
+        assert!(KeyPress::parse("nonsense").is_err());
+
+        assert_eq!(
+            parse_chord("g g"),
+            Ok(vec![press(KeyCode::Char('g')), press(KeyCode::Char('g'))])
+        );
+        assert_eq!(
+            KeyPress::parse("ctrl+alt+enter").unwrap().name(),
+            "<CTRL+ALT+ENTER>"
+        );
+        assert_eq!(KeyPress::parse("space").unwrap().name(), "<SPACE>");
+    }
+
+    #[test]
+    fn default_keys_test() {
+        let keymap = Keymap::new(&BTreeMap::new()).unwrap();
+
+        for (context, _, _) in CONTEXTS {
+            let mut chords: Vec<String> = keymap
+                .bindings(context)
+                .iter()
+                .flat_map(|binding| binding.chords.iter().map(|chord| chord_name(chord)))
+                .collect();
+            let chord_count = chords.len();
+            chords.sort();
+            chords.dedup();
+
+            assert_eq!(chords.len(), chord_count, "a key is bound twice");
+        }
+
+        assert_eq!(
+            keymap.feed(Context::TaskList, &mut vec![], press(KeyCode::Down)),
+            Input::Action(Action::SelectNext)
+        );
+        assert_eq!(keymap.key_for(Context::TaskList, Action::Quit), "'q'");
+        assert_eq!(
+            keymap.key_for(Context::TextInput, Action::Submit),
+            "<ENTER>"
+        );
+    }
+
+    #[test]
+    fn override_keys_test() {
+        let overrides = BTreeMap::from([(
+            "task_list".to_owned(),
+            BTreeMap::from([
+                (
+                    "select_next".to_owned(),
+                    vec!["ctrl+n".to_owned(), "g j".to_owned()],
+                ),
+                ("quit".to_owned(), vec![]),
+            ]),
+        )]);
+        let keymap = Keymap::new(&overrides).unwrap();
+
+        let mut pending = vec![];
+        assert_eq!(
+            keymap.feed(Context::TaskList, &mut pending, press(KeyCode::Char('j'))),
+            Input::Unbound(vec![press(KeyCode::Char('j'))])
+        );
+        assert_eq!(
+            keymap.feed(Context::TaskList, &mut pending, press(KeyCode::Char('g'))),
+            Input::Pending
+        );
+        assert_eq!(
+            keymap.feed(Context::TaskList, &mut pending, press(KeyCode::Char('j'))),
+            Input::Action(Action::SelectNext)
+        );
+        assert!(pending.is_empty());
+        assert_eq!(
+            keymap.feed(
+                Context::TaskList,
+                &mut pending,
+                KeyPress {
+                    code: KeyCode::Char('n'),
+                    modifiers: KeyModifiers::CONTROL
+                }
+            ),
+            Input::Action(Action::SelectNext)
+        );
+        assert_eq!(
+            keymap.feed(Context::TaskList, &mut pending, press(KeyCode::Char('q'))),
+            Input::Unbound(vec![press(KeyCode::Char('q'))])
+        );
+
+        let bad_action = BTreeMap::from([(
+            "task_list".to_owned(),
+            BTreeMap::from([("fly".to_owned(), vec!["f".to_owned()])]),
+        )]);
+        assert!(Keymap::new(&bad_action).is_err());
+
+        let bad_key = BTreeMap::from([(
+            "text_input".to_owned(),
+            BTreeMap::from([("submit".to_owned(), vec!["ctrl+".to_owned()])]),
+        )]);
+        assert!(Keymap::new(&bad_key).is_err());
+    }
+}
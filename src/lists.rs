@@ -0,0 +1,245 @@
+use platform_dirs::AppDirs;
+use std::{
+    fmt, fs,
+    io::{self, ErrorKind},
+    path::PathBuf,
+};
+
+use crate::{config::Config, database::Database};
+
+/// The name of the list kept in the database from the config, or TeaL's usual database
+pub const DEFAULT_LIST: &str = "default";
+
+/// Named task lists, like `work` and `home`. Apart from the default list, each list is kept in its
+/// own database file named after it in TeaL's data directory
+pub struct TaskLists {
+    default_database: PathBuf,
+    directory: PathBuf,
+    /// The list being used, or None if a database was picked with `--db` instead
+    pub current: Option<String>,
+    /// Where the database being used is
+    pub current_database: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum ListError {
+    InvalidName(String),
+    NotFound(String),
+    AlreadyExists(String),
+    /// The default list can't be renamed or deleted
+    DefaultList,
+    Io(io::Error),
+}
+
+impl From<io::Error> for ListError {
+    fn from(error: io::Error) -> Self {
+        ListError::Io(error)
+    }
+}
+
+impl fmt::Display for ListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListError::InvalidName(name) => write!(
+                f,
+                "'{}' can't be used as a list name, list names can only have letters, numbers, - and _",
+                name
+            ),
+            ListError::NotFound(name) => write!(f, "There's no list called {}", name),
+            ListError::AlreadyExists(name) => write!(f, "There's already a list called {}", name),
+            ListError::DefaultList => {
+                write!(f, "The {} list can't be renamed or deleted", DEFAULT_LIST)
+            }
+            ListError::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ListError {}
+
+impl TaskLists {
+    /// The lists for the given config, with the default list being used
+    pub fn new(config: &Config) -> Self {
+        let default_database = config
+            .database
+            .clone()
+            .unwrap_or_else(Database::default_path);
+        let directory = AppDirs::new(Some("TeaL"), true)
+            .unwrap()
+            .data_dir
+            .join("lists");
+
+        Self::in_directory(default_database, directory)
+    }
+
+    fn in_directory(default_database: PathBuf, directory: PathBuf) -> Self {
+        TaskLists {
+            current: Some(DEFAULT_LIST.to_owned()),
+            current_database: default_database.clone(),
+            default_database,
+            directory,
+        }
+    }
+
+    fn check_name(name: &str) -> Result<(), ListError> {
+        let is_valid = !name.is_empty()
+            && name.chars().all(|character| {
+                character.is_alphanumeric() || character == '-' || character == '_'
+            });
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(ListError::InvalidName(name.to_owned()))
+        }
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        self.directory.join(format!("{}.db", name))
+    }
+
+    /// The names of every list, starting with the default list and then in alphabetical order
+    pub fn names(&self) -> Result<Vec<String>, ListError> {
+        let mut names = vec![];
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return Ok(vec![DEFAULT_LIST.to_owned()])
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "db") {
+                if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                    names.push(name.to_owned());
+                }
+            }
+        }
+        names.sort();
+        names.insert(0, DEFAULT_LIST.to_owned());
+
+        Ok(names)
+    }
+
+    /// Where an existing list is kept
+    pub fn path(&self, name: &str) -> Result<PathBuf, ListError> {
+        if name == DEFAULT_LIST {
+            return Ok(self.default_database.clone());
+        }
+        Self::check_name(name)?;
+
+        let path = self.file(name);
+        if path.try_exists()? {
+            Ok(path)
+        } else {
+            Err(ListError::NotFound(name.to_owned()))
+        }
+    }
+
+    /// Switches to using an existing list, returning where it's kept
+    pub fn switch_to(&mut self, name: &str) -> Result<PathBuf, ListError> {
+        let path = self.path(name)?;
+        self.current = Some(name.to_owned());
+        self.current_database = path.clone();
+        Ok(path)
+    }
+
+    /// Uses the database at the given path rather than one of the lists
+    pub fn use_database(&mut self, path: PathBuf) {
+        self.current = None;
+        self.current_database = path;
+    }
+
+    /// Makes an empty file for a new list and returns where it is. The file still needs to be set
+    /// up as a database by [`Database::setup`]
+    pub fn create(&self, name: &str) -> Result<PathBuf, ListError> {
+        Self::check_name(name)?;
+        if name == DEFAULT_LIST || self.file(name).try_exists()? {
+            return Err(ListError::AlreadyExists(name.to_owned()));
+        }
+
+        fs::create_dir_all(&self.directory)?;
+        let path = self.file(name);
+        fs::File::create(&path)?;
+        Ok(path)
+    }
+
+    pub fn rename(&self, name: &str, new_name: &str) -> Result<(), ListError> {
+        if name == DEFAULT_LIST {
+            return Err(ListError::DefaultList);
+        }
+        let path = self.path(name)?;
+
+        Self::check_name(new_name)?;
+        if new_name == DEFAULT_LIST || self.file(new_name).try_exists()? {
+            return Err(ListError::AlreadyExists(new_name.to_owned()));
+        }
+
+        fs::rename(path, self.file(new_name))?;
+        Ok(())
+    }
+
+    /// Deletes a list along with every task in it
+    pub fn delete(&self, name: &str) -> Result<(), ListError> {
+        if name == DEFAULT_LIST {
+            return Err(ListError::DefaultList);
+        }
+
+        fs::remove_file(self.path(name)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manage_lists_test() {
+        let directory =
+            std::env::temp_dir().join(format!("teal-lists-test-{}", std::process::id()));
+        let mut lists =
+            TaskLists::in_directory(PathBuf::from("/somewhere/TeaL.db"), directory.clone());
+
+        assert_eq!(lists.names().unwrap(), vec![DEFAULT_LIST]);
+        assert_eq!(
+            lists.path(DEFAULT_LIST).unwrap(),
+            PathBuf::from("/somewhere/TeaL.db")
+        );
+
+        let work = lists.create("work").unwrap();
+        lists.create("home").unwrap();
+        assert!(work.exists());
+        assert_eq!(lists.names().unwrap(), vec![DEFAULT_LIST, "home", "work"]);
+        assert!(matches!(
+            lists.create("work"),
+            Err(ListError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            lists.create("../work"),
+            Err(ListError::InvalidName(_))
+        ));
+
+        lists.rename("work", "team").unwrap();
+        assert!(matches!(lists.path("work"), Err(ListError::NotFound(_))));
+        assert!(matches!(
+            lists.rename("team", "home"),
+            Err(ListError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            lists.rename(DEFAULT_LIST, "other"),
+            Err(ListError::DefaultList)
+        ));
+
+        lists.switch_to("team").unwrap();
+        assert_eq!(lists.current.as_deref(), Some("team"));
+        assert_eq!(lists.current_database, directory.join("team.db"));
+
+        lists.delete("home").unwrap();
+        assert_eq!(lists.names().unwrap(), vec![DEFAULT_LIST, "team"]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,10 +1,19 @@
-use eyre::Result;
-use std::env;
+use eyre::{eyre, Result};
+use std::{env, path::PathBuf};
 use ui::{teardown, DisplayingTasksData};
 
 mod cli;
+mod config;
 mod database;
+mod dates;
+mod durations;
+mod editor;
+mod keybindings;
+mod lists;
+mod migrations;
+mod recurrence;
 mod sorting;
+mod tags;
 mod ui;
 
 #[tokio::main]
@@ -14,29 +23,78 @@ async fn main() -> Result<()> {
 }
 
 async fn run() -> Result<()> {
-    let mut db = database::Database::new(None).await?;
+    let mut args: Vec<String> = env::args().collect();
+    let mut take_global_argument =
+        |name| cli::take_global_argument(&mut args, name).map_err(|error| eyre!(error));
+    let config_argument = take_global_argument("config")?;
+    let db_argument = take_global_argument("db")?;
+    let list_argument = take_global_argument("list")?;
 
-    db.setup().await?;
+    config::set(config::Config::load(config_argument.as_deref())?);
+    let config = config::get();
+
+    let mut lists = lists::TaskLists::new(config);
+    match (db_argument, list_argument) {
+        (Some(_), Some(_)) => return Err(eyre!("--db and --list can't be used together")),
+        (Some(db), None) => lists.use_database(PathBuf::from(db)),
+        (None, Some(list)) => {
+            lists.switch_to(&list).map_err(|error| match error {
+                lists::ListError::NotFound(_) => eyre!(
+                    "{}, run '{} lists create {}' to make it",
+                    error,
+                    args[0],
+                    list
+                ),
+                error => eyre!(error),
+            })?;
+        }
+        (None, None) => {}
+    }
 
-    let args: Vec<String> = env::args().collect();
+    let mut db =
+        database::Database::new(Some(lists.current_database.to_string_lossy().into_owned()))
+            .await?;
+
+    db.setup().await?;
 
     if args.len() < 2 {
+        let keymap =
+            keybindings::Keymap::new(&config.keys).map_err(|error| match &config.source {
+                Some(source) => eyre!("{} in {}", error, source.display()),
+                None => eyre!(error),
+            })?;
         let mut terminal = ui::setup()?;
 
-        let mut state = ui::States::DisplayingTasks(ui::DisplayingTasksStates::Normal, DisplayingTasksData {
-        selected_task: None,
-        command_palette_text: "Welcome to TeaL! Press 'n' to add a task, 'd' to remove a task or 'h' for more help".to_owned(),
-        search_string: None,
-    });
+        let mut state = ui::States::DisplayingTasks(
+            ui::DisplayingTasksStates::Normal,
+            DisplayingTasksData {
+                selected_task: None,
+                command_palette_text: format!(
+            "Welcome to TeaL! Press {} to add a task, {} to remove a task or {} for more help",
+            keymap.key_for(keybindings::Context::TaskList, keybindings::Action::AddTask),
+            keymap.key_for(keybindings::Context::TaskList, keybindings::Action::Remove),
+            keymap.key_for(keybindings::Context::TaskList, keybindings::Action::ShowHelp)
+        ),
+                search_string: None,
+                sort_order: config.list.sort,
+                running_timer: None,
+            },
+        );
         loop {
-            state = ui::display_state(state, &mut terminal, &mut db).await?;
+            state = ui::display_state(state, &mut terminal, &mut db, &keymap, &mut lists).await?;
             if state == ui::States::Quitting {
                 break;
             }
         }
 
-        teardown(&mut terminal)
+        teardown(&mut terminal)?;
     } else {
-        cli::run(db, args).await
+        cli::run(&mut db, &lists, args).await?;
     }
+
+    // Changes made by a statement which has not been stepped all the way through (for example an
+    // `INSERT ... RETURNING` which was only fetched once) are only committed when the statement is
+    // finalized, so make sure that happens before we exit
+    db.close().await?;
+    Ok(())
 }
@@ -1,3 +1,5 @@
+-- Databases created before TeaL had migrations are at version 0 but already contain this schema,
+-- so everything here must stay IF NOT EXISTS
 CREATE TABLE IF NOT EXISTS tasks (
 	id integer PRIMARY KEY AUTOINCREMENT,
 	description text NOT NULL,
@@ -0,0 +1,3 @@
+-- Both dates are unix timestamps. A date without a time of day is stored as the start of that day
+ALTER TABLE tasks ADD COLUMN due integer;
+ALTER TABLE tasks ADD COLUMN scheduled integer;
@@ -0,0 +1,2 @@
+-- 0 is no priority, going up to 3 for high priority. See database::PRIORITY_NAMES
+ALTER TABLE tasks ADD COLUMN priority integer NOT NULL DEFAULT 0;
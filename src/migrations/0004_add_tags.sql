@@ -0,0 +1,13 @@
+-- Tag names include their sigil, so `@work` (a context) and `+work` (a tag) are different tags
+CREATE TABLE tags (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	name text NOT NULL UNIQUE
+);
+
+CREATE TABLE task_tags (
+	task integer NOT NULL,
+	tag integer NOT NULL,
+	PRIMARY KEY(task, tag),
+	FOREIGN KEY(task) REFERENCES tasks(id) ON DELETE CASCADE,
+	FOREIGN KEY(tag) REFERENCES tags(id) ON DELETE CASCADE
+);
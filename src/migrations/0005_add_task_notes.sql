@@ -0,0 +1 @@
+ALTER TABLE tasks ADD COLUMN notes text NOT NULL DEFAULT '';
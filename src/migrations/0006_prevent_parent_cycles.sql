@@ -0,0 +1,22 @@
+-- no_self_parenting only covers inserts, and a task which already exists could otherwise be moved
+-- under itself or one of its own subtasks, making a cycle which would never be shown or deleted
+CREATE TRIGGER no_parent_cycles
+BEFORE UPDATE OF parent ON tasks
+FOR EACH ROW
+WHEN NEW.parent IS NOT NULL AND (
+    NEW.parent = NEW.id OR NEW.parent IN (
+        WITH RECURSIVE subtask_tree AS (
+            SELECT id
+            FROM tasks
+            WHERE parent = NEW.id
+        UNION ALL
+            SELECT subtasks.id
+            FROM tasks subtasks
+        INNER JOIN subtask_tree ON subtask_tree.id = subtasks.parent
+        )
+        SELECT id FROM subtask_tree
+    )
+)
+BEGIN
+    SELECT RAISE(ABORT, "A task can't be moved under itself or one of its subtasks");
+END;
@@ -0,0 +1,5 @@
+-- Tasks whose subtasks are hidden in the task list, so that it stays the same between runs
+CREATE TABLE collapsed_tasks (
+	task integer PRIMARY KEY NOT NULL,
+	FOREIGN KEY(task) REFERENCES tasks(id) ON DELETE CASCADE
+);
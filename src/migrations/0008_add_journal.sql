@@ -0,0 +1,115 @@
+-- A history of the changes made to the tasks, so that they can be undone and redone. Each entry in
+-- the journal is one thing that was done, like removing a task, and its changes are every row that
+-- was added, removed or changed by it, including rows that were removed by a cascade. Which tasks are
+-- collapsed is only how they're shown, so that isn't recorded
+--
+-- Rows are kept as JSON objects, so any migration which adds a column to one of these tables must
+-- recreate its triggers with the new column, and Database::restore_rows has to restore it too
+CREATE TABLE journal (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	description text NOT NULL,
+	-- Changes are only recorded while their entry is being recorded, so that undoing or redoing
+	-- an entry doesn't record anything itself
+	recording boolean NOT NULL DEFAULT true,
+	undone boolean NOT NULL DEFAULT false
+);
+
+CREATE TABLE journal_changes (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	entry integer NOT NULL,
+	table_name text NOT NULL,
+	-- The row before and after the change, or null if it didn't exist
+	before text,
+	after text,
+	FOREIGN KEY(entry) REFERENCES journal(id) ON DELETE CASCADE
+);
+
+CREATE TRIGGER journal_tasks_insert
+AFTER INSERT ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', NULL, json_object('id', NEW.id, 'description', NEW.description, 'complete', NEW.complete, 'parent', NEW.parent, 'due', NEW.due, 'scheduled', NEW.scheduled, 'priority', NEW.priority, 'notes', NEW.notes)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_tasks_update
+AFTER UPDATE ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', json_object('id', OLD.id, 'description', OLD.description, 'complete', OLD.complete, 'parent', OLD.parent, 'due', OLD.due, 'scheduled', OLD.scheduled, 'priority', OLD.priority, 'notes', OLD.notes), json_object('id', NEW.id, 'description', NEW.description, 'complete', NEW.complete, 'parent', NEW.parent, 'due', NEW.due, 'scheduled', NEW.scheduled, 'priority', NEW.priority, 'notes', NEW.notes)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_tasks_delete
+AFTER DELETE ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', json_object('id', OLD.id, 'description', OLD.description, 'complete', OLD.complete, 'parent', OLD.parent, 'due', OLD.due, 'scheduled', OLD.scheduled, 'priority', OLD.priority, 'notes', OLD.notes), NULL
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_tags_insert
+AFTER INSERT ON tags
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tags', NULL, json_object('id', NEW.id, 'name', NEW.name)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_tags_update
+AFTER UPDATE ON tags
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tags', json_object('id', OLD.id, 'name', OLD.name), json_object('id', NEW.id, 'name', NEW.name)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_tags_delete
+AFTER DELETE ON tags
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tags', json_object('id', OLD.id, 'name', OLD.name), NULL
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_task_tags_insert
+AFTER INSERT ON task_tags
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'task_tags', NULL, json_object('task', NEW.task, 'tag', NEW.tag)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_task_tags_update
+AFTER UPDATE ON task_tags
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'task_tags', json_object('task', OLD.task, 'tag', OLD.tag), json_object('task', NEW.task, 'tag', NEW.tag)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_task_tags_delete
+AFTER DELETE ON task_tags
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'task_tags', json_object('task', OLD.task, 'tag', OLD.tag), NULL
+    FROM journal
+    WHERE recording;
+END;
@@ -0,0 +1,56 @@
+-- Removed tasks are moved to the trash rather than being deleted straight away, so that they can be
+-- restored. This is when the task was removed, or null if it hasn't been
+ALTER TABLE tasks ADD COLUMN deleted integer;
+
+-- Tasks in the trash are hidden, so nothing else can be added or moved under them. This gives the
+-- same error as the parent not existing at all
+CREATE TRIGGER no_trashed_parents_on_insert
+BEFORE INSERT ON tasks
+FOR EACH ROW
+WHEN NEW.deleted IS NULL AND (SELECT deleted FROM tasks WHERE id = NEW.parent) IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, "FOREIGN KEY constraint failed");
+END;
+
+CREATE TRIGGER no_trashed_parents_on_update
+BEFORE UPDATE OF parent ON tasks
+FOR EACH ROW
+WHEN NEW.deleted IS NULL AND (SELECT deleted FROM tasks WHERE id = NEW.parent) IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, "FOREIGN KEY constraint failed");
+END;
+
+-- The journal has to record the new column too
+DROP TRIGGER journal_tasks_insert;
+DROP TRIGGER journal_tasks_update;
+DROP TRIGGER journal_tasks_delete;
+
+CREATE TRIGGER journal_tasks_insert
+AFTER INSERT ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', NULL, json_object('id', NEW.id, 'description', NEW.description, 'complete', NEW.complete, 'parent', NEW.parent, 'due', NEW.due, 'scheduled', NEW.scheduled, 'priority', NEW.priority, 'notes', NEW.notes, 'deleted', NEW.deleted)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_tasks_update
+AFTER UPDATE ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', json_object('id', OLD.id, 'description', OLD.description, 'complete', OLD.complete, 'parent', OLD.parent, 'due', OLD.due, 'scheduled', OLD.scheduled, 'priority', OLD.priority, 'notes', OLD.notes, 'deleted', OLD.deleted), json_object('id', NEW.id, 'description', NEW.description, 'complete', NEW.complete, 'parent', NEW.parent, 'due', NEW.due, 'scheduled', NEW.scheduled, 'priority', NEW.priority, 'notes', NEW.notes, 'deleted', NEW.deleted)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_tasks_delete
+AFTER DELETE ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', json_object('id', OLD.id, 'description', OLD.description, 'complete', OLD.complete, 'parent', OLD.parent, 'due', OLD.due, 'scheduled', OLD.scheduled, 'priority', OLD.priority, 'notes', OLD.notes, 'deleted', OLD.deleted), NULL
+    FROM journal
+    WHERE recording;
+END;
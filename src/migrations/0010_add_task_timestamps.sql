@@ -0,0 +1,75 @@
+-- When each task was created, last changed and marked as done. Tasks which existed before this
+-- migration don't have them, so they're left as null
+ALTER TABLE tasks ADD COLUMN created integer;
+ALTER TABLE tasks ADD COLUMN updated integer;
+ALTER TABLE tasks ADD COLUMN completed integer;
+
+-- TeaL sets these itself so that the tasks it returns are up to date, but these triggers keep them
+-- right when the tasks are changed by anything else, like a direct SQL edit. They only step in when
+-- the timestamps weren't set by whatever changed the task
+CREATE TRIGGER set_task_created
+AFTER INSERT ON tasks
+FOR EACH ROW
+WHEN NEW.created IS NULL
+BEGIN
+    UPDATE tasks SET created = CAST(strftime('%s', 'now') AS integer),
+                     updated = COALESCE(NEW.updated, CAST(strftime('%s', 'now') AS integer)),
+                     completed = CASE
+                         WHEN NEW.complete THEN COALESCE(NEW.completed, CAST(strftime('%s', 'now') AS integer))
+                     END
+    WHERE id = NEW.id;
+END;
+
+-- The timestamps themselves are left out, so that setting one of them doesn't count as a change.
+-- Columns added to tasks later need to be added here
+CREATE TRIGGER set_task_updated
+AFTER UPDATE OF description, complete, parent, due, scheduled, priority, notes, deleted ON tasks
+FOR EACH ROW
+WHEN NEW.updated IS OLD.updated
+BEGIN
+    UPDATE tasks SET updated = CAST(strftime('%s', 'now') AS integer) WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER set_task_completed
+AFTER UPDATE OF complete ON tasks
+FOR EACH ROW
+WHEN NEW.complete IS NOT OLD.complete AND NEW.completed IS OLD.completed
+BEGIN
+    UPDATE tasks SET completed = CASE WHEN NEW.complete THEN CAST(strftime('%s', 'now') AS integer) END
+    WHERE id = NEW.id;
+END;
+
+-- The journal has to record the new columns too
+DROP TRIGGER journal_tasks_insert;
+DROP TRIGGER journal_tasks_update;
+DROP TRIGGER journal_tasks_delete;
+
+CREATE TRIGGER journal_tasks_insert
+AFTER INSERT ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', NULL, json_object('id', NEW.id, 'description', NEW.description, 'complete', NEW.complete, 'parent', NEW.parent, 'due', NEW.due, 'scheduled', NEW.scheduled, 'priority', NEW.priority, 'notes', NEW.notes, 'deleted', NEW.deleted, 'created', NEW.created, 'updated', NEW.updated, 'completed', NEW.completed)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_tasks_update
+AFTER UPDATE ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', json_object('id', OLD.id, 'description', OLD.description, 'complete', OLD.complete, 'parent', OLD.parent, 'due', OLD.due, 'scheduled', OLD.scheduled, 'priority', OLD.priority, 'notes', OLD.notes, 'deleted', OLD.deleted, 'created', OLD.created, 'updated', OLD.updated, 'completed', OLD.completed), json_object('id', NEW.id, 'description', NEW.description, 'complete', NEW.complete, 'parent', NEW.parent, 'due', NEW.due, 'scheduled', NEW.scheduled, 'priority', NEW.priority, 'notes', NEW.notes, 'deleted', NEW.deleted, 'created', NEW.created, 'updated', NEW.updated, 'completed', NEW.completed)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_tasks_delete
+AFTER DELETE ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', json_object('id', OLD.id, 'description', OLD.description, 'complete', OLD.complete, 'parent', OLD.parent, 'due', OLD.due, 'scheduled', OLD.scheduled, 'priority', OLD.priority, 'notes', OLD.notes, 'deleted', OLD.deleted, 'created', OLD.created, 'updated', OLD.updated, 'completed', OLD.completed), NULL
+    FROM journal
+    WHERE recording;
+END;
@@ -0,0 +1,48 @@
+-- How often a task repeats, like `every 2 weeks`, or null if it doesn't. See recurrence.rs for
+-- the rules that can be written here
+ALTER TABLE tasks ADD COLUMN recurrence text;
+
+DROP TRIGGER set_task_updated;
+CREATE TRIGGER set_task_updated
+AFTER UPDATE OF description, complete, parent, due, scheduled, priority, notes, deleted, recurrence
+ON tasks
+FOR EACH ROW
+WHEN NEW.updated IS OLD.updated
+BEGIN
+    UPDATE tasks SET updated = CAST(strftime('%s', 'now') AS integer) WHERE id = NEW.id;
+END;
+
+-- The journal has to record the new column too
+DROP TRIGGER journal_tasks_insert;
+DROP TRIGGER journal_tasks_update;
+DROP TRIGGER journal_tasks_delete;
+
+CREATE TRIGGER journal_tasks_insert
+AFTER INSERT ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', NULL, json_object('id', NEW.id, 'description', NEW.description, 'complete', NEW.complete, 'parent', NEW.parent, 'due', NEW.due, 'scheduled', NEW.scheduled, 'priority', NEW.priority, 'notes', NEW.notes, 'deleted', NEW.deleted, 'created', NEW.created, 'updated', NEW.updated, 'completed', NEW.completed, 'recurrence', NEW.recurrence)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_tasks_update
+AFTER UPDATE ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', json_object('id', OLD.id, 'description', OLD.description, 'complete', OLD.complete, 'parent', OLD.parent, 'due', OLD.due, 'scheduled', OLD.scheduled, 'priority', OLD.priority, 'notes', OLD.notes, 'deleted', OLD.deleted, 'created', OLD.created, 'updated', OLD.updated, 'completed', OLD.completed, 'recurrence', OLD.recurrence), json_object('id', NEW.id, 'description', NEW.description, 'complete', NEW.complete, 'parent', NEW.parent, 'due', NEW.due, 'scheduled', NEW.scheduled, 'priority', NEW.priority, 'notes', NEW.notes, 'deleted', NEW.deleted, 'created', NEW.created, 'updated', NEW.updated, 'completed', NEW.completed, 'recurrence', NEW.recurrence)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_tasks_delete
+AFTER DELETE ON tasks
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'tasks', json_object('id', OLD.id, 'description', OLD.description, 'complete', OLD.complete, 'parent', OLD.parent, 'due', OLD.due, 'scheduled', OLD.scheduled, 'priority', OLD.priority, 'notes', OLD.notes, 'deleted', OLD.deleted, 'created', OLD.created, 'updated', OLD.updated, 'completed', OLD.completed, 'recurrence', OLD.recurrence), NULL
+    FROM journal
+    WHERE recording;
+END;
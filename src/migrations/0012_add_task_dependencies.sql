@@ -0,0 +1,49 @@
+-- Tasks which can't be done until other tasks are. Unlike subtasks, a task can be blocked by any
+-- other task, wherever it is in the tree
+CREATE TABLE task_dependencies (
+	task integer NOT NULL,
+	blocked_by integer NOT NULL,
+	PRIMARY KEY(task, blocked_by),
+	FOREIGN KEY(task) REFERENCES tasks(id) ON DELETE CASCADE,
+	FOREIGN KEY(blocked_by) REFERENCES tasks(id) ON DELETE CASCADE
+);
+
+-- A task blocked by itself, or by a task that it's blocking, could never be done
+CREATE TRIGGER no_dependency_cycles
+BEFORE INSERT ON task_dependencies
+FOR EACH ROW
+WHEN NEW.task = NEW.blocked_by OR NEW.task IN (
+    WITH RECURSIVE blockers AS (
+        SELECT blocked_by AS id
+        FROM task_dependencies
+        WHERE task = NEW.blocked_by
+    UNION
+        SELECT task_dependencies.blocked_by
+        FROM task_dependencies
+    INNER JOIN blockers ON blockers.id = task_dependencies.task
+    )
+    SELECT id FROM blockers
+)
+BEGIN
+    SELECT RAISE(ABORT, "A task can't be blocked by itself or a task it's blocking");
+END;
+
+CREATE TRIGGER journal_task_dependencies_insert
+AFTER INSERT ON task_dependencies
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'task_dependencies', NULL, json_object('task', NEW.task, 'blocked_by', NEW.blocked_by)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_task_dependencies_delete
+AFTER DELETE ON task_dependencies
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'task_dependencies', json_object('task', OLD.task, 'blocked_by', OLD.blocked_by), NULL
+    FROM journal
+    WHERE recording;
+END;
@@ -0,0 +1,43 @@
+-- Time spent on tasks, as stretches of time from when work started to when it stopped. An entry
+-- which hasn't stopped yet is a timer that's still running
+CREATE TABLE time_entries (
+	id integer PRIMARY KEY AUTOINCREMENT,
+	task integer NOT NULL,
+	started integer NOT NULL,
+	stopped integer,
+	CHECK (stopped IS NULL OR stopped >= started),
+	FOREIGN KEY(task) REFERENCES tasks(id) ON DELETE CASCADE
+);
+
+-- Only one timer can run at a time
+CREATE UNIQUE INDEX one_running_timer ON time_entries (stopped IS NULL) WHERE stopped IS NULL;
+
+CREATE TRIGGER journal_time_entries_insert
+AFTER INSERT ON time_entries
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'time_entries', NULL, json_object('id', NEW.id, 'task', NEW.task, 'started', NEW.started, 'stopped', NEW.stopped)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_time_entries_update
+AFTER UPDATE ON time_entries
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'time_entries', json_object('id', OLD.id, 'task', OLD.task, 'started', OLD.started, 'stopped', OLD.stopped), json_object('id', NEW.id, 'task', NEW.task, 'started', NEW.started, 'stopped', NEW.stopped)
+    FROM journal
+    WHERE recording;
+END;
+
+CREATE TRIGGER journal_time_entries_delete
+AFTER DELETE ON time_entries
+FOR EACH ROW
+BEGIN
+    INSERT INTO journal_changes (entry, table_name, before, after)
+    SELECT id, 'time_entries', json_object('id', OLD.id, 'task', OLD.task, 'started', OLD.started, 'stopped', OLD.stopped), NULL
+    FROM journal
+    WHERE recording;
+END;
@@ -0,0 +1,210 @@
+use color_eyre::Report;
+use sqlx::{Connection, SqliteConnection};
+
+/// Every migration that has been made to the schema, in the order they need to be applied
+///
+/// The schema version of a database is the number of these which have been applied to it, and is
+/// stored in SQLite's `user_version` pragma. Never edit or reorder a migration once it has been
+/// released, as that would leave existing databases with a different schema to new ones; add a new
+/// migration to the end of the list instead
+const MIGRATIONS: &[&str] = &[
+    include_str!("./0001_create_tasks.sql"),
+    include_str!("./0002_add_task_dates.sql"),
+    include_str!("./0003_add_task_priority.sql"),
+    include_str!("./0004_add_tags.sql"),
+    include_str!("./0005_add_task_notes.sql"),
+    include_str!("./0006_prevent_parent_cycles.sql"),
+    include_str!("./0007_add_collapsed_tasks.sql"),
+    include_str!("./0008_add_journal.sql"),
+    include_str!("./0009_add_trash.sql"),
+    include_str!("./0010_add_task_timestamps.sql"),
+    include_str!("./0011_add_task_recurrence.sql"),
+    include_str!("./0012_add_task_dependencies.sql"),
+    include_str!("./0013_add_complete_with_subtasks.sql"),
+    include_str!("./0014_add_time_entries.sql"),
+    include_str!("./0015_add_task_estimates.sql"),
+];
+
+/// The schema version that this build of TeaL expects the database to be at
+pub const LATEST_VERSION: i64 = MIGRATIONS.len() as i64;
+
+pub async fn get_version(connection: &mut SqliteConnection) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(connection)
+        .await
+}
+
+/// Upgrades the database to the given schema version, returning the version it was at beforehand
+///
+/// Each migration is applied in its own transaction along with the version bump, so if a migration
+/// fails the database will be left at the last version that was successfully reached rather than
+/// somewhere in-between. Databases that are already newer than the target are refused, as this
+/// build of TeaL can't know what has changed in them
+pub async fn migrate_to(connection: &mut SqliteConnection, target: i64) -> eyre::Result<i64> {
+    if !(0..=LATEST_VERSION).contains(&target) {
+        return Err(Report::msg(format!(
+            "Schema version {} doesn't exist, the latest version is {}",
+            target, LATEST_VERSION
+        )));
+    }
+
+    let original_version = get_version(connection).await?;
+
+    if original_version > LATEST_VERSION {
+        return Err(Report::msg(format!(
+            "The database is at schema version {}, but this version of TeaL only understands up to version {}. Please update TeaL to open it",
+            original_version, LATEST_VERSION
+        )));
+    }
+
+    if original_version > target {
+        return Err(Report::msg(format!(
+            "The database is already at schema version {}, which is newer than version {}",
+            original_version, target
+        )));
+    }
+
+    for version in original_version..target {
+        let mut transaction = connection.begin().await?;
+
+        sqlx::query(MIGRATIONS[version as usize])
+            .execute(&mut transaction)
+            .await?;
+        // Pragmas can't take bound parameters, but this is an integer we produced so it's safe to
+        // format straight into the query
+        sqlx::query(&format!("PRAGMA user_version = {}", version + 1))
+            .execute(&mut transaction)
+            .await?;
+
+        transaction.commit().await?;
+    }
+
+    Ok(original_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+    use crate::database::{Database, NewTask};
+
+    /// Copies a database from the tests directory into a temporary file, so that tests can modify it
+    /// without changing the original
+    fn copy_test_database(name: &str, test_name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "TeaL-{}-{}-{}.db",
+            test_name,
+            std::process::id(),
+            name
+        ));
+
+        fs::copy(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("tests")
+                .join(name),
+            &path,
+        )
+        .unwrap();
+
+        path
+    }
+
+    #[tokio::test]
+    async fn upgrade_through_every_version_test() {
+        // testing-v0.db is a copy of testing.db from before TeaL had migrations, and so has the same
+        // schema as a database that an older version of TeaL would have created
+        let path = copy_test_database("testing-v0.db", "upgrade_through_every_version");
+        let mut db = Database::new(Some(path.to_str().unwrap().to_owned()))
+            .await
+            .unwrap();
+
+        assert_eq!(get_version(db.connection()).await.unwrap(), 0);
+
+        sqlx::query(
+            "INSERT INTO tasks VALUES (null, 'A task from before migrations', false, null)",
+        )
+        .execute(&mut *db.connection())
+        .await
+        .unwrap();
+
+        for version in 1..=LATEST_VERSION {
+            assert_eq!(
+                migrate_to(db.connection(), version).await.unwrap(),
+                version - 1
+            );
+            assert_eq!(get_version(db.connection()).await.unwrap(), version);
+        }
+
+        let tasks = db.list_tasks(true).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].description, "A task from before migrations");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn testing_database_is_up_to_date_test() {
+        // The sqlx query macros are checked against testing.db at compile time, so it must always
+        // have the latest schema
+        let path = copy_test_database("testing.db", "testing_database_is_up_to_date");
+        let mut db = Database::new(Some(path.to_str().unwrap().to_owned()))
+            .await
+            .unwrap();
+
+        assert_eq!(get_version(db.connection()).await.unwrap(), LATEST_VERSION);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn setup_is_idempotent_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+        db.add_task(&NewTask::new("A test task", None))
+            .await
+            .unwrap();
+        db.setup().await.unwrap();
+
+        assert_eq!(get_version(db.connection()).await.unwrap(), LATEST_VERSION);
+        assert_eq!(db.list_tasks(true).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn refuse_newer_database_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        sqlx::query(&format!("PRAGMA user_version = {}", LATEST_VERSION + 1))
+            .execute(&mut *db.connection())
+            .await
+            .unwrap();
+
+        assert!(db.setup().await.is_err());
+        assert_eq!(
+            get_version(db.connection()).await.unwrap(),
+            LATEST_VERSION + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_migration_is_rolled_back_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        // A view called tasks stops the first migration from creating the table, and then makes it
+        // fail as triggers can't run before inserts on views
+        sqlx::query("CREATE VIEW tasks AS SELECT 1 AS id")
+            .execute(&mut *db.connection())
+            .await
+            .unwrap();
+
+        assert!(db.setup().await.is_err());
+        assert_eq!(get_version(db.connection()).await.unwrap(), 0);
+    }
+}
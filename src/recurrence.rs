@@ -0,0 +1,240 @@
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Duration, Local, Months, NaiveDateTime, TimeZone, Weekday};
+
+use crate::dates::{parse_weekday, to_local};
+
+/// How often a recurring task comes back, like `every 2 weeks` or `every mon,thu`
+///
+/// When a recurring task is marked as done, a copy of it and its subtasks is made for the next time
+/// it's due, and the rule moves over to the copy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub interval: Interval,
+    /// Whether the next copy is due an interval after the task was done, rather than an interval
+    /// after the task was due
+    pub after_completion: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Interval {
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+    /// On each of these days of the week, in order from Monday
+    Weekdays(Vec<Weekday>),
+}
+
+const AFTER_COMPLETION: &str = " after completion";
+
+impl Recurrence {
+    /// Parses a rule like `daily`, `weekly`, `monthly`, `yearly`, `every 3 days`, `every 2 weeks`,
+    /// `every mon,wed,fri`, `weekdays` or `weekends`. Any of these can be followed by
+    /// `after completion`, so that the next copy is due an interval after the task was done
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim().to_lowercase();
+        let invalid = || {
+            format!(
+                "'{}' isn't a repeat rule TeaL understands, try something like daily, every 2 weeks, \
+                every mon,thu or monthly after completion",
+                text
+            )
+        };
+
+        let (rule, after_completion) = match text.strip_suffix(AFTER_COMPLETION) {
+            Some(rule) => (rule.trim(), true),
+            None => (text.as_str(), false),
+        };
+
+        let interval = match rule {
+            "daily" => Interval::Days(1),
+            "weekly" => Interval::Weeks(1),
+            "monthly" => Interval::Months(1),
+            "yearly" => Interval::Years(1),
+            "weekdays" => Interval::Weekdays(vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ]),
+            "weekends" => Interval::Weekdays(vec![Weekday::Sat, Weekday::Sun]),
+            _ => {
+                let rule = rule.strip_prefix("every ").ok_or_else(invalid)?.trim();
+                let (amount, unit) = match rule.split_once(' ') {
+                    Some((amount, unit)) if amount.chars().all(|char| char.is_ascii_digit()) => {
+                        (amount.parse::<u32>().map_err(|_| invalid())?, unit.trim())
+                    }
+                    _ => (1, rule),
+                };
+
+                match unit.trim_end_matches('s') {
+                    _ if amount == 0 => return Err(invalid()),
+                    "day" => Interval::Days(amount),
+                    "week" => Interval::Weeks(amount),
+                    "month" => Interval::Months(amount),
+                    "year" => Interval::Years(amount),
+                    _ if amount == 1 => {
+                        let mut weekdays = unit
+                            .split(',')
+                            .map(|day| parse_weekday(day.trim()))
+                            .collect::<Option<Vec<_>>>()
+                            .ok_or_else(invalid)?;
+                        weekdays.sort_by_key(Weekday::num_days_from_monday);
+                        weekdays.dedup();
+                        Interval::Weekdays(weekdays)
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+        };
+
+        Ok(Recurrence {
+            interval,
+            after_completion,
+        })
+    }
+
+    /// The first time after `date` that the task comes back
+    fn step(&self, date: NaiveDateTime) -> Option<NaiveDateTime> {
+        match &self.interval {
+            Interval::Days(days) => date.checked_add_signed(Duration::days((*days).into())),
+            Interval::Weeks(weeks) => date.checked_add_signed(Duration::weeks((*weeks).into())),
+            Interval::Months(months) => date.checked_add_months(Months::new(*months)),
+            Interval::Years(years) => date.checked_add_months(Months::new(years.checked_mul(12)?)),
+            Interval::Weekdays(weekdays) => (1..=7)
+                .filter_map(|days| date.checked_add_signed(Duration::days(days)))
+                .find(|date| weekdays.contains(&date.weekday())),
+        }
+    }
+
+    /// When the next copy of a task is due, given when the task itself was due (or scheduled) and
+    /// that it was done at `now`. The time of day it was due at is kept
+    ///
+    /// Tasks which are due on a fixed schedule skip any times which have already gone by, so that
+    /// doing a task late doesn't leave its next copy overdue. Tasks without a date come back an
+    /// interval after they were done
+    pub fn next_date(&self, due: Option<i64>, now: DateTime<Local>) -> Option<i64> {
+        let today = now.date_naive().and_time(chrono::NaiveTime::MIN);
+
+        let next = match due.map(|due| to_local(due).naive_local()) {
+            Some(due) if !self.after_completion => {
+                let mut next = self.step(due)?;
+                while next < today {
+                    next = self.step(next)?;
+                }
+                next
+            }
+            Some(due) => self.step(now.date_naive().and_time(due.time()))?,
+            None => self.step(today)?,
+        };
+
+        Local
+            .from_local_datetime(&next)
+            .earliest()
+            .map(|next| next.timestamp())
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |amount: &u32, unit: &str| match amount {
+            1 => format!("every {}", unit),
+            amount => format!("every {} {}s", amount, unit),
+        };
+
+        match &self.interval {
+            Interval::Days(days) => write!(f, "{}", plural(days, "day"))?,
+            Interval::Weeks(weeks) => write!(f, "{}", plural(weeks, "week"))?,
+            Interval::Months(months) => write!(f, "{}", plural(months, "month"))?,
+            Interval::Years(years) => write!(f, "{}", plural(years, "year"))?,
+            Interval::Weekdays(weekdays) => write!(
+                f,
+                "every {}",
+                weekdays
+                    .iter()
+                    .map(|weekday| weekday.to_string().to_lowercase())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )?,
+        }
+
+        if self.after_completion {
+            write!(f, "{}", AFTER_COMPLETION)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_recurrence_test() {
+        for (text, expected) in [
+            ("daily", "every day"),
+            ("Every 2 weeks", "every 2 weeks"),
+            (
+                "every month after completion",
+                "every month after completion",
+            ),
+            ("every fri, mon", "every mon,fri"),
+            ("weekdays", "every mon,tue,wed,thu,fri"),
+            ("every 3 years", "every 3 years"),
+        ] {
+            let recurrence = Recurrence::parse(text).unwrap();
+            assert_eq!(recurrence.to_string(), expected);
+            // What's shown can be read back in
+            assert_eq!(Recurrence::parse(expected).unwrap(), recurrence);
+        }
+
+        assert!(Recurrence::parse("sometimes").is_err());
+        assert!(Recurrence::parse("every 0 days").is_err());
+        assert!(Recurrence::parse("every 2 mondays").is_err());
+    }
+
+    #[test]
+    fn next_date_test() {
+        // 2023-01-04 is a Wednesday
+        let now = at(2023, 1, 4, 12, 0);
+        let due = |year, month, day, hour| Some(at(year, month, day, hour, 0).timestamp());
+
+        let weekly = Recurrence::parse("weekly").unwrap();
+        assert_eq!(
+            weekly.next_date(due(2023, 1, 3, 9), now),
+            due(2023, 1, 10, 9)
+        );
+        // Going by the schedule skips weeks that have already gone by
+        assert_eq!(
+            weekly.next_date(due(2022, 12, 1, 9), now),
+            due(2023, 1, 5, 9)
+        );
+
+        let after_completion = Recurrence::parse("every 2 days after completion").unwrap();
+        assert_eq!(
+            after_completion.next_date(due(2022, 12, 1, 9), now),
+            due(2023, 1, 6, 9)
+        );
+        assert_eq!(after_completion.next_date(None, now), due(2023, 1, 6, 0));
+
+        let monthly = Recurrence::parse("monthly").unwrap();
+        assert_eq!(
+            monthly.next_date(due(2023, 1, 31, 0), now),
+            due(2023, 2, 28, 0)
+        );
+
+        let mondays_and_fridays = Recurrence::parse("every mon,fri").unwrap();
+        assert_eq!(
+            mondays_and_fridays.next_date(due(2023, 1, 6, 0), now),
+            due(2023, 1, 9, 0)
+        );
+    }
+}
@@ -1,8 +1,94 @@
 use std::cmp::Reverse;
 
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use serde::{Deserialize, Serialize};
 
-use crate::database::Task;
+use crate::database::{Task, TaskTree};
+
+/// The orders that tasks can be listed in. Whatever the order, ties are broken by ID so that tasks
+/// which were added first come first
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum SortOrder {
+    #[default]
+    Id,
+    Priority,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 2] = [SortOrder::Id, SortOrder::Priority];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortOrder::Id => "id",
+            SortOrder::Priority => "priority",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|order| order.name().eq_ignore_ascii_case(text))
+            .ok_or_else(|| {
+                format!(
+                    "Invalid sort order: {}, expected one of {}",
+                    text,
+                    Self::ALL.map(|order| order.name()).join(", ")
+                )
+            })
+    }
+
+    /// The order after this one, wrapping around to the start after the last
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|order| order == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn key(&self, id: i64, priority: i64) -> (Reverse<i64>, i64) {
+        match self {
+            SortOrder::Id => (Reverse(0), id),
+            SortOrder::Priority => (Reverse(priority), id),
+        }
+    }
+}
+
+impl TryFrom<String> for SortOrder {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        Self::parse(&text)
+    }
+}
+
+impl From<SortOrder> for &'static str {
+    fn from(order: SortOrder) -> Self {
+        order.name()
+    }
+}
+
+pub fn sort(mut tasks: Vec<Task>, order: SortOrder) -> Vec<Task> {
+    tasks.sort_by_key(|task| order.key(task.id, task.priority));
+    tasks
+}
+
+/// Sorts the children of every task in a tree, so that subtasks are ordered within their parent
+pub fn sort_tree(tree: &mut TaskTree, order: SortOrder) {
+    tree.children
+        .sort_by_key(|child| order.key(child.id, child.priority));
+
+    for child in tree.children.iter_mut() {
+        sort_tree(child, order);
+    }
+}
+
+/// Removes the subtasks in a tree which are done, along with everything inside them
+pub fn remove_done(tree: &mut TaskTree) {
+    tree.children.retain(|child| !child.complete);
+
+    for child in tree.children.iter_mut() {
+        remove_done(child);
+    }
+}
 
 pub fn search(term: &str, tasks: Vec<Task>) -> Vec<Task> {
     let matcher = SkimMatcherV2::default();
@@ -10,8 +96,15 @@ pub fn search(term: &str, tasks: Vec<Task>) -> Vec<Task> {
     let mut scored_tasks = tasks
         .into_iter()
         .filter_map(|task| {
-            matcher
-                .fuzzy_match(&task.description, term)
+            // A match in the description is worth more than one in the notes, as notes are longer
+            // and so are more likely to match by accident
+            let description_score = matcher.fuzzy_match(&task.description, term);
+            let notes_score = matcher
+                .fuzzy_match(&task.notes, term)
+                .map(|score| score / 2);
+
+            description_score
+                .max(notes_score)
                 .map(|score| (task, score))
         })
         .collect::<Vec<(Task, i64)>>();
@@ -55,3 +148,84 @@ pub fn completion_filter(tasks: Vec<Task>) -> CompletionFilterResult {
     }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Database, NewTask, TaskUpdate};
+
+    #[tokio::test]
+    async fn search_notes_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let task = db
+            .add_task(&NewTask::new("Write report", None))
+            .await
+            .unwrap();
+        db.add_task(&NewTask::new("Quarterly figures", None))
+            .await
+            .unwrap();
+
+        assert!(search("quarterly", db.list_tasks(true).await.unwrap())
+            .iter()
+            .all(|found_task| found_task.id != task.id));
+
+        db.update_task(
+            task.id,
+            &TaskUpdate {
+                notes: Some("Include the quarterly figures\nand a summary".to_owned()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let found_tasks = search("quarterly", db.list_tasks(true).await.unwrap());
+        assert_eq!(found_tasks.len(), 2);
+        // The task with a matching description should come before the one with matching notes
+        assert_eq!(found_tasks[1].id, task.id);
+    }
+
+    #[tokio::test]
+    async fn sort_by_priority_test() {
+        let mut db = Database::new(Some("sqlite::memory:".to_owned()))
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+
+        let parent = db.add_task(&NewTask::new("Parent", None)).await.unwrap();
+        for (description, priority) in [("Low", 1), ("None", 0), ("High", 3), ("Also low", 1)] {
+            db.add_task(&NewTask {
+                priority,
+                ..NewTask::new(description, Some(parent.id))
+            })
+            .await
+            .unwrap();
+        }
+
+        let sorted_tasks = sort(db.list_tasks(true).await.unwrap(), SortOrder::Priority);
+        assert_eq!(
+            sorted_tasks
+                .iter()
+                .map(|task| task.description.as_str())
+                .collect::<Vec<_>>(),
+            vec!["High", "Low", "Also low", "Parent", "None"]
+        );
+
+        let mut task_tree = db.list_subtasks(parent.id).await.unwrap();
+        sort_tree(&mut task_tree, SortOrder::Priority);
+        assert_eq!(
+            task_tree
+                .children
+                .iter()
+                .map(|task| task.description.as_str())
+                .collect::<Vec<_>>(),
+            vec!["High", "Low", "Also low", "None"]
+        );
+    }
+}
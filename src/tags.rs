@@ -0,0 +1,65 @@
+/// Whether a word is a tag, like `+release-1.2`, or a context, like `@work`
+fn is_tag(word: &str) -> bool {
+    (word.starts_with('+') || word.starts_with('@')) && word.len() > 1
+}
+
+/// Adds the `+` sigil to a tag name if it doesn't already have a sigil, so that `work` and `+work`
+/// refer to the same tag
+pub fn normalize_tag(name: &str) -> String {
+    if is_tag(name) {
+        name.to_owned()
+    } else {
+        format!("+{}", name)
+    }
+}
+
+/// Splits the tags and contexts out of a task's text, returning the description without them and
+/// the tags in the order they were written. For example, `Buy milk @home +errands` is split into
+/// `Buy milk` and `["@home", "+errands"]`
+pub fn split_tags(text: &str) -> (String, Vec<String>) {
+    let mut tags: Vec<String> = vec![];
+    let mut description_words = vec![];
+
+    for word in text.split_whitespace() {
+        if is_tag(word) {
+            if !tags.iter().any(|tag| tag == word) {
+                tags.push(word.to_owned());
+            }
+        } else {
+            description_words.push(word);
+        }
+    }
+
+    (description_words.join(" "), tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_tags_test() {
+        assert_eq!(
+            split_tags("Buy milk @home +errands"),
+            (
+                "Buy milk".to_owned(),
+                vec!["@home".to_owned(), "+errands".to_owned()]
+            )
+        );
+        assert_eq!(
+            split_tags("+release-1.2 Fix 1 + 1 @work +release-1.2"),
+            (
+                "Fix 1 + 1".to_owned(),
+                vec!["+release-1.2".to_owned(), "@work".to_owned()]
+            )
+        );
+        assert_eq!(split_tags("No tags"), ("No tags".to_owned(), vec![]));
+    }
+
+    #[test]
+    fn normalize_tag_test() {
+        assert_eq!(normalize_tag("work"), "+work");
+        assert_eq!(normalize_tag("+work"), "+work");
+        assert_eq!(normalize_tag("@work"), "@work");
+    }
+}
@@ -1,41 +1,57 @@
+use chrono::Local;
 use color_eyre::Report;
 use eyre::Result;
 use std::{
-    collections::HashSet,
-    io::{stdout, Stdout}, cmp::Ordering,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    io::{stdout, Stdout},
 };
 
 use crate::{
-    database::{self, Database, FlatTaskTreeElement, Task},
-    sorting::search,
+    config,
+    database::{
+        self, Database, FlatTaskTreeElement, MoveTaskError, NewTask, Task, TaskTree, TaskUpdate,
+        TimeEntry,
+    },
+    dates,
+    durations::format_duration,
+    editor,
+    keybindings::{chord_name, Action, Binding, Context, Input, KeyPress, Keymap},
+    lists::TaskLists,
+    sorting::{remove_done, search, sort, sort_tree, SortOrder},
+    tags::split_tags,
 };
 use crossterm::{
     self,
-    event::{read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
-use sqlx::Error;
 use tui::{
     backend::CrosstermBackend,
     layout::Rect,
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Span, Spans},
     widgets::{self, Block, Borders, ListState, Paragraph},
     Frame, Terminal,
 };
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum DisplayingTasksStates {
     Normal,
     Create,
+    CreateSubtask,
+    Edit,
     Search,
+    Move,
+    SwitchList,
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum DisplayingTaskFullscreenStates {
     Normal,
-    // TODO: Create,
+    Create,
+    Edit,
 }
 
 pub trait StateData {
@@ -47,6 +63,10 @@ pub struct DisplayingTasksData {
     pub selected_task: Option<i64>,
     pub command_palette_text: String,
     pub search_string: Option<String>,
+    pub sort_order: SortOrder,
+    /// The timer that's running, shown in the mode line. This is kept up to date by
+    /// [`display_state`]
+    pub running_timer: Option<TimeEntry>,
 }
 
 #[derive(PartialEq, Clone)]
@@ -54,6 +74,10 @@ pub struct DisplayingTaskFullscreenData {
     pub command_palette_text: String,
     pub task_id: i64,
     pub selected_task: Option<i64>,
+    pub notes_scroll: u16,
+    /// The timer that's running, shown in the mode line. This is kept up to date by
+    /// [`display_state`]
+    pub running_timer: Option<TimeEntry>,
 }
 
 impl StateData for DisplayingTasksData {
@@ -97,8 +121,35 @@ fn task_index_from_id(tasks: &[Task], id: Option<i64>) -> Option<usize> {
     None
 }
 
+/// The ID of the task after the selected one, wrapping around to the first task at the end of the
+/// list
+fn select_next(tasks: &[Task], selected: Option<i64>) -> Option<i64> {
+    if tasks.is_empty() {
+        return selected;
+    }
+
+    match task_index_from_id(tasks, selected) {
+        Some(index) if index + 1 < tasks.len() => Some(tasks[index + 1].id),
+        _ => Some(tasks[0].id),
+    }
+}
+
+/// The ID of the task before the selected one, wrapping around to the last task at the start of the
+/// list
+fn select_previous(tasks: &[Task], selected: Option<i64>) -> Option<i64> {
+    if tasks.is_empty() {
+        return selected;
+    }
+
+    match task_index_from_id(tasks, selected) {
+        None | Some(0) => Some(tasks[tasks.len() - 1].id),
+        Some(index) => Some(tasks[index - 1].id),
+    }
+}
+
 /// Draw the command palette and mode line at the bottom of the screen, returning the remaining screen space
 fn draw_status_lines(frame: &mut Frame<CrosstermBackend<Stdout>>, state: &States) -> Rect {
+    let theme = &config::get().theme;
     let mut total_size = frame.size();
     if total_size.height < 2 {
         return total_size; // No space for status lines
@@ -135,20 +186,44 @@ fn draw_status_lines(frame: &mut Frame<CrosstermBackend<Stdout>>, state: &States
                 }
             }
             DisplayingTasksStates::Create => "Append",
+            DisplayingTasksStates::CreateSubtask => "Append subtask",
+            DisplayingTasksStates::Edit => "Edit",
             DisplayingTasksStates::Search => "Search",
+            DisplayingTasksStates::Move => "Move",
+            DisplayingTasksStates::SwitchList => "Switch list",
+        },
+        States::DisplayingTaskFullscreen(inner_state, _) => match inner_state {
+            DisplayingTaskFullscreenStates::Normal => "Task",
+            DisplayingTaskFullscreenStates::Create => "Append subtask",
+            DisplayingTaskFullscreenStates::Edit => "Edit",
         },
-        States::DisplayingTaskFullscreen(_, _) => "Task",
         States::Quitting => return frame.size(),
     };
 
-    let mode_line_text = vec![Span::styled(
+    let mut mode_line_text = vec![Span::styled(
         format!(" {} ", mode),
         Style::default()
-            .fg(Color::Black)
-            .bg(Color::Cyan)
+            .fg(theme.accent_text.tui())
+            .bg(theme.accent.tui())
             .add_modifier(Modifier::BOLD),
     )];
 
+    let running_timer = match state {
+        States::DisplayingTasks(_, state_data) => state_data.running_timer.as_ref(),
+        States::DisplayingTaskFullscreen(_, state_data) => state_data.running_timer.as_ref(),
+        States::Quitting => None,
+    };
+    if let Some(timer) = running_timer {
+        mode_line_text.push(Span::styled(
+            format!(
+                " Timing task {} · {} ",
+                timer.task,
+                format_duration(timer.length(Local::now().timestamp()))
+            ),
+            Style::default().fg(theme.accent.tui()),
+        ));
+    }
+
     let mode_line = Paragraph::new(Spans::from(mode_line_text));
     let mode_line_area = Rect {
         x: 0,
@@ -169,32 +244,154 @@ fn filter_tasks(tasks: &Vec<Task>, state_data: &DisplayingTasksData) -> Vec<Task
         filtered_tasks = search(query, filtered_tasks);
     }
 
+    // Search results are already ordered by relevance, so only reorder them if another order was
+    // explicitly picked
+    if state_data.search_string.is_none() || state_data.sort_order != SortOrder::Id {
+        filtered_tasks = sort(filtered_tasks, state_data.sort_order);
+    }
+
     filtered_tasks
 }
 
+/// A line in the task list
+struct TaskListRow {
+    task: Task,
+    /// The lines connecting the task to its parent, or None if the list is flat rather than a tree
+    tree_prefix: Option<String>,
+    /// How many of a collapsed task's subtasks are done and how many subtasks it has, or None if the
+    /// task isn't collapsed
+    collapsed_progress: Option<(usize, usize)>,
+    /// The tasks which aren't done yet and are blocking this one
+    blocked_by: Vec<i64>,
+    /// How many seconds of work are estimated to be left on the task and its subtasks
+    remaining_estimate: i64,
+}
+
+impl From<Task> for TaskListRow {
+    fn from(task: Task) -> Self {
+        // Search results are listed without their subtasks, so only their own estimate counts
+        let remaining_estimate = if task.complete {
+            0
+        } else {
+            task.estimate.unwrap_or_default()
+        };
+        TaskListRow {
+            task,
+            tree_prefix: None,
+            collapsed_progress: None,
+            blocked_by: vec![],
+            remaining_estimate,
+        }
+    }
+}
+
+/// Turn a task tree into rows for the task list, leaving out the subtasks of collapsed tasks
+fn tree_rows(task_tree: &TaskTree, collapsed: &HashSet<i64>) -> Vec<TaskListRow> {
+    let elements: Vec<FlatTaskTreeElement> = task_tree.clone().into();
+    let mut rows = vec![];
+    let mut hidden_below_level = None;
+
+    for (index, (element, prefix)) in elements.iter().zip(tree_prefixes(&elements)).enumerate() {
+        if let Some(level) = hidden_below_level {
+            if element.level > level {
+                continue;
+            }
+            hidden_below_level = None;
+        }
+
+        let subtasks = elements[index + 1..]
+            .iter()
+            .take_while(|subtask| subtask.level > element.level);
+        let collapsed_progress =
+            if collapsed.contains(&element.task.id) && subtasks.clone().next().is_some() {
+                hidden_below_level = Some(element.level);
+                Some((
+                    subtasks
+                        .clone()
+                        .filter(|subtask| subtask.task.complete)
+                        .count(),
+                    subtasks.count(),
+                ))
+            } else {
+                None
+            };
+
+        rows.push(TaskListRow {
+            task: element.task.clone(),
+            tree_prefix: Some(prefix),
+            collapsed_progress,
+            blocked_by: vec![],
+            remaining_estimate: element.remaining_estimate.unwrap_or_default(),
+        });
+    }
+
+    rows
+}
+
+/// List the rows of the task list. Normally this is a tree of the top-level tasks and their
+/// subtasks, but while searching it's a flat list of every task that matches. Tasks that are done
+/// are left out if the config hides them
+async fn list_rows(
+    db: &mut database::Database,
+    state_data: &DisplayingTasksData,
+) -> Result<Vec<TaskListRow>> {
+    let mut blockers = db.list_open_blockers().await?;
+    let mut rows = list_rows_without_blockers(db, state_data).await?;
+    for row in &mut rows {
+        row.blocked_by = blockers.remove(&row.task.id).unwrap_or_default();
+    }
+
+    Ok(rows)
+}
+
+/// The rows of the task list, before which tasks are blocked is filled in by [`list_rows`]
+async fn list_rows_without_blockers(
+    db: &mut database::Database,
+    state_data: &DisplayingTasksData,
+) -> Result<Vec<TaskListRow>> {
+    let hide_done = config::get().list.hide_done;
+
+    if state_data.search_string.is_some() {
+        return Ok(filter_tasks(&db.list_tasks(true).await?, state_data)
+            .into_iter()
+            .filter(|task| !(hide_done && task.complete))
+            .map(TaskListRow::from)
+            .collect());
+    }
+
+    let collapsed = db.list_collapsed_tasks().await?;
+    let mut rows = vec![];
+    for task in filter_tasks(&db.list_tasks(false).await?, state_data) {
+        if hide_done && task.complete {
+            continue;
+        }
+
+        let mut task_tree = db.list_subtasks(task.id).await?;
+        if hide_done {
+            remove_done(&mut task_tree);
+        }
+        sort_tree(&mut task_tree, state_data.sort_order);
+        rows.extend(tree_rows(&task_tree, &collapsed));
+    }
+
+    Ok(rows)
+}
+
 fn draw_tasks(
-    tasks: &Vec<Task>,
+    rows: &[TaskListRow],
+    tags: &HashMap<i64, Vec<String>>,
     frame: &mut Frame<CrosstermBackend<Stdout>>,
     remaining_space: Rect,
     selected: Option<i64>,
-    state_data: &DisplayingTasksData,
-    already_filtered: bool,
 ) {
+    let theme = &config::get().theme;
     let block = Block::default()
         .title("┤ Your tasks ├")
         .borders(Borders::ALL);
 
     let mut list_items = Vec::new();
 
-    let filtered_tasks;
-    let owned_filtered_tasks;
-    if already_filtered {
-        filtered_tasks = tasks;
-    } else {
-        owned_filtered_tasks = filter_tasks(tasks, state_data);
-        filtered_tasks = &owned_filtered_tasks;
-    }
-    if filtered_tasks.is_empty() {
+    if rows.is_empty() {
         let warning = widgets::Paragraph::new(Span::styled(
             " There's nothing here, try removing your filters or press `n` to add a new task",
             Style::default().add_modifier(Modifier::ITALIC),
@@ -203,14 +400,105 @@ fn draw_tasks(
         frame.render_widget(warning, remaining_space);
         return;
     }
-    for task in filtered_tasks {
-        let mut text_parts = vec![Span::from(format!(" {}", task.description))];
+    let now = Local::now();
+    for row in rows {
+        let task = &row.task;
+        let overdue = !task.complete
+            && task
+                .due
+                .map(|due| dates::is_overdue(due, now))
+                .unwrap_or(false);
+
+        let mut text_parts = vec![];
+
+        if let Some(ref tree_prefix) = row.tree_prefix {
+            text_parts.push(Span::raw(tree_prefix.trim_end().to_owned()));
+        }
 
-        if let Some(parent_id) = task.parent {
+        if task.priority > 0 {
+            text_parts.push(Span::styled(
+                format!(" {}", "!".repeat(task.priority as usize)),
+                Style::default()
+                    .fg(theme.priority(task.priority).tui())
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+
+        text_parts.push(if overdue {
+            Span::styled(
+                format!(" {}", task.description),
+                Style::default()
+                    .fg(theme.overdue.tui())
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::from(format!(" {}", task.description))
+        });
+
+        if !task.complete && !row.blocked_by.is_empty() {
+            text_parts.push(Span::styled(
+                format!(
+                    " [blocked by {}]",
+                    row.blocked_by
+                        .iter()
+                        .map(i64::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                Style::default().fg(theme.not_done.tui()),
+            ));
+        }
+
+        if let Some((done, total)) = row.collapsed_progress {
+            text_parts.push(Span::styled(
+                format!(" [{}/{} subtasks done]", done, total),
+                Style::default().fg(theme.accent.tui()),
+            ));
+        }
+
+        if row.remaining_estimate > 0 {
+            text_parts.push(Span::styled(
+                format!(" ~{} left", format_duration(row.remaining_estimate)),
+                Style::default().fg(theme.muted.tui()),
+            ));
+        }
+
+        for tag in tags.get(&task.id).unwrap_or(&vec![]) {
+            text_parts.push(Span::raw(" "));
+            text_parts.push(Span::styled(
+                tag.clone(),
+                if tag.starts_with('@') {
+                    Style::default().fg(theme.context.tui())
+                } else {
+                    Style::default().fg(theme.tag.tui())
+                },
+            ));
+        }
+
+        if let Some(due) = task.due {
+            text_parts.push(Span::styled(
+                format!(
+                    " {} {}",
+                    if overdue { "overdue since" } else { "due" },
+                    dates::format_date(due)
+                ),
+                if overdue {
+                    Style::default()
+                        .fg(theme.overdue.tui())
+                        .add_modifier(Modifier::ITALIC)
+                } else {
+                    Style::default()
+                        .fg(theme.muted.tui())
+                        .add_modifier(Modifier::ITALIC)
+                },
+            ))
+        }
+
+        if let (Some(parent_id), None) = (task.parent, &row.tree_prefix) {
             text_parts.push(Span::styled(
                 format!(" (child of task {})", parent_id),
                 Style::default()
-                    .fg(Color::DarkGray)
+                    .fg(theme.muted.tui())
                     .add_modifier(Modifier::ITALIC),
             ))
         }
@@ -220,16 +508,14 @@ fn draw_tasks(
     let list = widgets::List::new(list_items)
         .highlight_style(
             Style::default()
-                .bg(Color::Cyan)
-                .fg(Color::Black)
+                .bg(theme.accent.tui())
+                .fg(theme.accent_text.tui())
                 .add_modifier(Modifier::BOLD),
         )
         .block(block);
 
     let mut state = ListState::default();
-    if let Some(task_id) = selected {
-        state.select(task_index_from_id(filtered_tasks, Some(task_id)));
-    }
+    state.select(rows.iter().position(|row| Some(row.task.id) == selected));
     frame.render_stateful_widget(list, remaining_space, &mut state)
 }
 
@@ -244,6 +530,28 @@ pub fn teardown(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()>
     Ok(())
 }
 
+/// Leaves the TUI to let the user edit some text in their editor (see [`editor::edit_text`]), and
+/// then comes back once they're done
+fn edit_in_external_editor(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    text: &str,
+    file_name: &str,
+) -> Result<String> {
+    teardown(terminal)?;
+
+    let edited_text = editor::edit_text(text, file_name);
+
+    crossterm::terminal::enable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableMouseCapture
+    )?;
+    terminal.clear()?;
+
+    edited_text
+}
+
 enum BoxDrawing {
     EndOfList,
     Indented,
@@ -251,194 +559,483 @@ enum BoxDrawing {
     Dedented,
 }
 
-pub async fn display_task_fullscreen(
-    db: &mut database::Database,
-    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    state_data: DisplayingTaskFullscreenData,
-) -> Result<States, Report> {
-    loop {
-        let task_tree = db.list_subtasks(state_data.task_id).await?;
+/// Work out the box drawing which connects each task in a flattened task tree to its parent, like
+/// `   ├─ `, so that the tree can be drawn as an indented list
+fn tree_prefixes(elements: &[FlatTaskTreeElement]) -> Vec<String> {
+    let padded_elements = std::iter::once(None)
+        .chain(elements.iter().map(Some))
+        .chain(std::iter::once(None))
+        .collect::<Vec<Option<&FlatTaskTreeElement>>>();
 
-        terminal.draw(|frame| {
-            let remaining_space = draw_status_lines(
-                frame,
-                &States::DisplayingTaskFullscreen(
-                    DisplayingTaskFullscreenStates::Normal,
-                    state_data.clone(),
-                ),
-            );
+    let mut all_indent_lines: Vec<HashSet<usize>> = vec![HashSet::new()];
 
-            let description = widgets::Paragraph::new(task_tree.description.clone())
-                .style(Style::default().add_modifier(Modifier::BOLD));
-            frame.render_widget(
-                description,
-                Rect {
-                    x: 1,
-                    y: 1,
-                    width: task_tree.description.len().try_into().unwrap(),
-                    height: 1,
-                },
-            );
+    for line in elements.iter().skip(1) {
+        let mut previous_indent_lines = all_indent_lines[all_indent_lines.len() - 1].clone();
 
-            let completion_state = match task_tree.complete {
-                true => widgets::Paragraph::new("(Done)").style(
-                    Style::default()
-                        .fg(Color::Green)
-                        .add_modifier(Modifier::ITALIC),
+        if line.last_under_parent {
+            previous_indent_lines.remove(&line.level);
+        } else {
+            previous_indent_lines.insert(line.level);
+        }
+
+        all_indent_lines.push(previous_indent_lines);
+    }
+
+    padded_elements
+        .windows(3)
+        .zip(all_indent_lines)
+        .map(|lines| {
+            if let ([line_before, line, line_after], indent_lines) = lines {
+                // Windows(3) must *always* return 3 elements here
+                let line = line.unwrap();
+
+                let box_drawing_top = match line_before {
+                    None => BoxDrawing::EndOfList,
+                    Some(line_before) => match line_before.level.cmp(&line.level) {
+                        Ordering::Equal => BoxDrawing::Equal,
+                        Ordering::Greater => BoxDrawing::Indented,
+                        Ordering::Less => BoxDrawing::Dedented,
+                    },
+                };
+
+                let box_drawing_bottom = match line_after {
+                    None => BoxDrawing::EndOfList,
+                    Some(line_after) => match line_after.level.cmp(&line.level) {
+                        Ordering::Less => BoxDrawing::Dedented,
+                        Ordering::Greater => BoxDrawing::Indented,
+                        Ordering::Equal => BoxDrawing::Equal,
+                    },
+                };
+
+                let box_drawing_character =
+                    match (box_drawing_top, box_drawing_bottom, line.last_under_parent) {
+                        (BoxDrawing::EndOfList, BoxDrawing::Dedented, _) => {
+                            unreachable!()
+                        }
+                        (BoxDrawing::EndOfList, _, _) => "",
+                        (_, BoxDrawing::Indented, false) => "├─",
+                        (
+                            _,
+                            BoxDrawing::EndOfList | BoxDrawing::Indented | BoxDrawing::Dedented,
+                            _,
+                        ) => "└─",
+                        (_, BoxDrawing::Equal, _) => "├─",
+                    };
+
+                format!(
+                    "{}{}{} ",
+                    if line.level == 0 { "" } else { "   " },
+                    (0..line.level)
+                        .skip(1)
+                        .map(|level| {
+                            if indent_lines.contains(&level) {
+                                "│    "
+                            } else {
+                                "     "
+                            }
+                        })
+                        .collect::<Vec<&str>>()
+                        .join(""),
+                    box_drawing_character,
+                )
+            } else {
+                unreachable!()
+            }
+        })
+        .collect()
+}
+
+/// Draw a task along with its notes and subtasks, taking up the whole screen, and returning the
+/// space that was left after drawing the status lines. `progress` is how many of each task's subtasks
+/// are done, from [`Database::list_progress`], and `time_spent` is how long was spent on each task
+/// with and without its subtasks, from [`Database::list_time_spent`]
+fn draw_task_fullscreen(
+    frame: &mut Frame<CrosstermBackend<Stdout>>,
+    task_tree: &TaskTree,
+    progress: &HashMap<i64, (usize, usize)>,
+    time_spent: &HashMap<i64, (i64, i64)>,
+    state: DisplayingTaskFullscreenStates,
+    state_data: &DisplayingTaskFullscreenData,
+) -> Rect {
+    let theme = &config::get().theme;
+    let remaining_space = draw_status_lines(
+        frame,
+        &States::DisplayingTaskFullscreen(state, state_data.clone()),
+    );
+
+    let description = widgets::Paragraph::new(task_tree.description.clone())
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    frame.render_widget(
+        description,
+        Rect {
+            x: 1,
+            y: 1,
+            width: task_tree.description.len().try_into().unwrap(),
+            height: 1,
+        },
+    );
+
+    let completion_state = match task_tree.complete {
+        true => widgets::Paragraph::new("(Done)").style(
+            Style::default()
+                .fg(theme.done.tui())
+                .add_modifier(Modifier::ITALIC),
+        ),
+        false => widgets::Paragraph::new("(Not done)").style(
+            Style::default()
+                .fg(theme.not_done.tui())
+                .add_modifier(Modifier::ITALIC),
+        ),
+    };
+    frame.render_widget(
+        completion_state,
+        Rect {
+            x: <usize as TryInto<u16>>::try_into(task_tree.description.len()).unwrap() + 3,
+            y: 1,
+            width: 10,
+            height: 1,
+        },
+    );
+
+    let details: Vec<String> = task_tree
+        .recurrence
+        .iter()
+        .map(|rule| format!("Repeats {}", rule))
+        .chain(
+            [
+                ("Created", task_tree.created),
+                ("Updated", task_tree.updated),
+                ("Done", task_tree.completed),
+            ]
+            .into_iter()
+            .filter_map(|(name, timestamp)| {
+                timestamp.map(|timestamp| format!("{} {}", name, dates::format_date(timestamp)))
+            }),
+        )
+        .chain(time_spent.get(&task_tree.id).map(|&(own, with_subtasks)| {
+            match own == with_subtasks {
+                true => format!("Time spent {}", format_duration(own)),
+                false => format!(
+                    "Time spent {} ({} with subtasks)",
+                    format_duration(own),
+                    format_duration(with_subtasks)
                 ),
-                false => widgets::Paragraph::new("(Not done)").style(
-                    Style::default()
-                        .fg(Color::Red)
-                        .add_modifier(Modifier::ITALIC),
+            }
+        }))
+        .chain(task_tree.estimate.map(|estimate| {
+            // The estimate doesn't count subtasks, so neither does the time it's compared with
+            match time_spent.get(&task_tree.id).map(|(own, _)| own - estimate) {
+                Some(over) if over >= 60 => format!(
+                    "Estimate {} ({} over)",
+                    format_duration(estimate),
+                    format_duration(over)
                 ),
-            };
-            frame.render_widget(
-                completion_state,
-                Rect {
-                    x: <usize as TryInto<u16>>::try_into(task_tree.description.len()).unwrap() + 3,
-                    y: 1,
-                    width: 10,
-                    height: 1,
-                },
+                Some(under) if under <= -60 => format!(
+                    "Estimate {} ({} under)",
+                    format_duration(estimate),
+                    format_duration(-under)
+                ),
+                _ => format!("Estimate {}", format_duration(estimate)),
+            }
+        }))
+        .chain(
+            Some(task_tree.remaining_estimate())
+                .filter(|remaining| *remaining > 0 && !task_tree.children.is_empty())
+                .map(|remaining| format!("{} left with subtasks", format_duration(remaining))),
+        )
+        .collect();
+    frame.render_widget(
+        widgets::Paragraph::new(details.join("  ·  "))
+            .style(Style::default().fg(theme.muted.tui())),
+        Rect {
+            x: 1,
+            y: 2,
+            width: remaining_space.width.saturating_sub(1),
+            height: 1,
+        },
+    );
+
+    let notes_height = if task_tree.notes.is_empty() {
+        0
+    } else {
+        remaining_space.height.saturating_sub(5) / 2
+    };
+
+    if notes_height > 0 {
+        let notes = widgets::Paragraph::new(task_tree.notes.clone())
+            .wrap(widgets::Wrap { trim: false })
+            .scroll((state_data.notes_scroll, 0))
+            .block(
+                widgets::Block::default()
+                    .borders(Borders::ALL)
+                    .title("┤ Notes ├"),
             );
+        frame.render_widget(
+            notes,
+            Rect {
+                x: remaining_space.x,
+                y: remaining_space.y + 3,
+                width: remaining_space.width,
+                height: notes_height,
+            },
+        );
+    }
+    let subtasks_offset = if notes_height > 0 {
+        3 + notes_height
+    } else {
+        5
+    };
 
-            let task_list_border = widgets::Block::default()
-                .borders(Borders::ALL)
-                .title("┤ Subtasks ├");
+    let task_list_border =
+        widgets::Block::default()
+            .borders(Borders::ALL)
+            .title(match progress.get(&task_tree.id) {
+                Some((done, total)) => format!("┤ Subtasks · {}/{} done ├", done, total),
+                None => "┤ Subtasks ├".to_owned(),
+            });
+
+    let elements: Vec<FlatTaskTreeElement> = task_tree.clone().into();
+    let task_list = widgets::List::new(
+        elements
+            .iter()
+            .zip(tree_prefixes(&elements))
+            .map(|(line, prefix)| {
+                let mut spans = vec![
+                    Span::raw(prefix),
+                    Span::styled(
+                        line.task.description.clone(),
+                        if line.level == 0 {
+                            Style::default().fg(theme.accent.tui())
+                        } else {
+                            Style::default()
+                        },
+                    ),
+                ];
+                // The task's own progress is already in the title
+                if let Some((done, total)) = progress.get(&line.task.id).filter(|_| line.level > 0)
+                {
+                    spans.push(Span::styled(
+                        format!(" {}/{}", done, total),
+                        Style::default().fg(theme.muted.tui()),
+                    ));
+                }
+                // Subtasks show the time spent on them and the work left, along with their own
+                // subtasks
+                if let Some((_, with_subtasks)) =
+                    time_spent.get(&line.task.id).filter(|_| line.level > 0)
+                {
+                    spans.push(Span::styled(
+                        format!(" · {} spent", format_duration(*with_subtasks)),
+                        Style::default().fg(theme.muted.tui()),
+                    ));
+                }
+                if let Some(remaining) = line
+                    .remaining_estimate
+                    .filter(|remaining| *remaining > 0 && line.level > 0)
+                {
+                    spans.push(Span::styled(
+                        format!(" · ~{} left", format_duration(remaining)),
+                        Style::default().fg(theme.muted.tui()),
+                    ));
+                }
+                Spans::from(spans)
+            })
+            .map(widgets::ListItem::new)
+            .collect::<Vec<widgets::ListItem>>(),
+    )
+    .highlight_style(
+        Style::default()
+            .bg(theme.accent.tui())
+            .fg(theme.accent_text.tui())
+            .add_modifier(Modifier::BOLD),
+    )
+    .block(task_list_border);
+
+    let mut list_state = ListState::default();
+    list_state.select(task_index_from_id(
+        &flatten_task_tree(task_tree),
+        state_data.selected_task,
+    ));
+
+    frame.render_stateful_widget(
+        task_list,
+        Rect {
+            x: remaining_space.x,
+            y: remaining_space.y + subtasks_offset,
+            width: remaining_space.width,
+            height: remaining_space.height.saturating_sub(subtasks_offset),
+        },
+        &mut list_state,
+    );
 
-            let flat_task_tree = vec![None]
-                .into_iter()
-                .chain(task_tree.clone().into_iter().map(Some))
-                .chain(vec![None].into_iter())
-                .collect::<Vec<Option<FlatTaskTreeElement>>>();
+    remaining_space
+}
 
-            let mut all_indent_lines: Vec<HashSet<usize>> = vec![HashSet::new()];
+/// Every task in a task tree, in the order they're shown in the fullscreen view
+fn flatten_task_tree(task_tree: &TaskTree) -> Vec<Task> {
+    task_tree
+        .clone()
+        .into_iter()
+        .map(|element| element.task)
+        .collect()
+}
 
-            for line in task_tree.clone().into_iter().skip(1) {
-                let mut previous_indent_lines =
-                    all_indent_lines[all_indent_lines.len() - 1].clone();
+pub async fn display_task_fullscreen(
+    db: &mut database::Database,
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
+    mut state_data: DisplayingTaskFullscreenData,
+) -> Result<States, Report> {
+    let mut pending = vec![];
+    // The blocked task which was just tried to be marked as done, see Action::ToggleCompletion
+    let mut forcing_completion = None;
 
-                if line.last_under_parent {
-                    previous_indent_lines.remove(&line.level);
-                } else {
-                    previous_indent_lines.insert(line.level);
-                }
+    loop {
+        let task_tree = db.list_subtasks(state_data.task_id).await?;
+        let progress = db.list_progress().await?;
+        let time_spent = db.list_time_spent(Local::now().timestamp()).await?;
+        let tasks = flatten_task_tree(&task_tree);
 
-                all_indent_lines.push(previous_indent_lines);
-            }
-
-            let task_list = widgets::List::new(
-                flat_task_tree
-                    .windows(3)
-                    .zip(all_indent_lines)
-                    .map(|lines| {
-                        if let ([line_before, line, line_after], indent_lines) = lines {
-                            // Windows(3) must *always* return 3 elements here
-                            let line = line.as_ref().unwrap();
-
-                            let box_drawing_top = match line_before {
-                                None => BoxDrawing::EndOfList,
-                                Some(line_before) => {
-                                    match line_before.level.cmp(&line.level) {
-                                        Ordering::Equal => BoxDrawing::Equal,
-                                        Ordering::Greater => BoxDrawing::Indented,
-                                        Ordering::Less => BoxDrawing::Dedented,
-                                    }
-                                }
-                            };
-
-                            let box_drawing_bottom = match line_after {
-                                None => BoxDrawing::EndOfList,
-                                Some(line_after) => match line_after.level.cmp(&line.level) {
-                                    Ordering::Less => BoxDrawing::Dedented,
-                                    Ordering::Greater => BoxDrawing::Indented,
-                                    Ordering::Equal => BoxDrawing::Equal,
-                                },
-                            };
-
-                            let box_drawing_character =
-                                match (box_drawing_top, box_drawing_bottom, line.last_under_parent)
-                                {
-                                    (BoxDrawing::EndOfList, BoxDrawing::Dedented, _) => {
-                                        unreachable!()
-                                    }
-                                    (BoxDrawing::EndOfList, _, _) => "",
-                                    (_, BoxDrawing::Indented, false) => "├─",
-                                    (
-                                        _,
-                                        BoxDrawing::EndOfList
-                                        | BoxDrawing::Indented
-                                        | BoxDrawing::Dedented,
-                                        _,
-                                    ) => "└─",
-                                    (_, BoxDrawing::Equal, _) => "├─",
-                                };
-
-                            Spans::from(vec![
-                                Span::raw(format!(
-                                    "{}{}{} ",
-                                    if line.level == 0 { "" } else { "   " },
-                                    (0..line.level)
-                                        .skip(1)
-                                        .map(|level| {
-                                            if indent_lines.contains(&level) {
-                                                "│    "
-                                            } else {
-                                                "     "
-                                            }
-                                        })
-                                        .collect::<Vec<&str>>()
-                                        .join(""),
-                                    box_drawing_character,
-                                )),
-                                Span::styled(
-                                    line.task.description.clone(),
-                                    if line.level == 0 {
-                                        Style::default().fg(Color::Cyan)
-                                    } else {
-                                        Style::default()
-                                    },
-                                ),
-                            ])
-                        } else {
-                            unreachable!()
-                        }
-                    })
-                    .map(widgets::ListItem::new)
-                    .collect::<Vec<widgets::ListItem>>(),
+        let draw = |frame: &mut Frame<CrosstermBackend<Stdout>>| {
+            draw_task_fullscreen(
+                frame,
+                &task_tree,
+                &progress,
+                &time_spent,
+                DisplayingTaskFullscreenStates::Normal,
+                &state_data,
             )
-            .block(task_list_border);
-
-            frame.render_stateful_widget(
-                task_list,
-                Rect {
-                    x: remaining_space.x,
-                    y: remaining_space.y + 5,
-                    width: remaining_space.width,
-                    height: remaining_space.height - 5,
-                },
-                &mut ListState::default(),
-            );
+        };
+        terminal.draw(|frame| {
+            draw(frame);
         })?;
 
-        match read()? {
-            Event::FocusGained => todo!(),
-            Event::FocusLost => todo!(),
-            Event::Key(event) => match event.code {
-                KeyCode::Char(' ') => db.set_completion(task_tree.id, !task_tree.complete).await?,
-                KeyCode::Char('q') => break,
-                _ => continue,
-            },
+        let key = match read()? {
+            Event::Key(event) => KeyPress::from(event),
+            _ => continue,
+        };
+
+        let forcing = forcing_completion.take();
+        match keymap.feed(Context::TaskFullscreen, &mut pending, key) {
+            Input::Action(Action::ShowHelp) => {
+                show_help(terminal, keymap.bindings(Context::TaskFullscreen), draw)?;
+            }
+            Input::Action(Action::ToggleCompletion) => {
+                let task_index = task_index_from_id(&tasks, state_data.selected_task);
+                let task = &tasks[task_index.unwrap_or(0)];
+                let blocked_by = if task.complete {
+                    vec![]
+                } else {
+                    db.list_open_blockers()
+                        .await?
+                        .remove(&task.id)
+                        .unwrap_or_default()
+                };
+
+                // Pressing the key again straight after being warned marks the task as done anyway
+                if blocked_by.is_empty() || forcing == Some(task.id) {
+                    db.set_completion(task.id, !task.complete).await?;
+                } else {
+                    state_data.command_palette_text = format!(
+                        "This task is blocked by {}, press {} again to mark it as done anyway",
+                        blocked_by
+                            .iter()
+                            .map(|blocker| format!("task {}", blocker))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        keymap.key_for(Context::TaskFullscreen, Action::ToggleCompletion)
+                    );
+                    forcing_completion = Some(task.id);
+                    continue;
+                }
+            }
+            Input::Action(Action::SelectNext) => {
+                state_data.selected_task = select_next(&tasks, state_data.selected_task);
+            }
+            Input::Action(Action::SelectPrevious) => {
+                state_data.selected_task = select_previous(&tasks, state_data.selected_task);
+            }
+            Input::Action(Action::Open) => {
+                if let Some(selected_task) = state_data.selected_task {
+                    state_data.task_id = selected_task;
+                    state_data.selected_task = None;
+                    state_data.notes_scroll = 0;
+                }
+            }
+            Input::Action(Action::GoUp) => {
+                // Go up to the parent, keeping the task we came from selected
+                match task_tree.parent {
+                    Some(parent) => {
+                        state_data.selected_task = Some(state_data.task_id);
+                        state_data.task_id = parent;
+                        state_data.notes_scroll = 0;
+                    }
+                    None => break,
+                }
+            }
+            Input::Action(Action::EditNotes) => {
+                let notes = match edit_in_external_editor(
+                    terminal,
+                    &task_tree.notes,
+                    &format!("task-{}-notes.md", task_tree.id),
+                ) {
+                    Ok(notes) => notes,
+                    Err(error) => {
+                        state_data.command_palette_text = error.to_string();
+                        continue;
+                    }
+                };
+                state_data.notes_scroll = 0;
+                db.update_task(
+                    task_tree.id,
+                    &TaskUpdate {
+                        notes: Some(notes),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            }
+            Input::Action(action @ (Action::Undo | Action::Redo)) => {
+                state_data.command_palette_text = undo_or_redo(db, action == Action::Undo).await?;
+                // Undoing adding this task, or redoing its removal, leaves nothing to show
+                if db.list_path(state_data.task_id).await?.is_empty() {
+                    break;
+                }
+            }
+            Input::Action(Action::ScrollNotesDown) => {
+                state_data.notes_scroll = state_data.notes_scroll.saturating_add(1);
+            }
+            Input::Action(Action::ScrollNotesUp) => {
+                state_data.notes_scroll = state_data.notes_scroll.saturating_sub(1);
+            }
+            Input::Action(Action::Edit) => {
+                return Ok(States::DisplayingTaskFullscreen(
+                    DisplayingTaskFullscreenStates::Edit,
+                    state_data,
+                ))
+            }
+            Input::Action(Action::AddSubtask) => {
+                return Ok(States::DisplayingTaskFullscreen(
+                    DisplayingTaskFullscreenStates::Create,
+                    state_data,
+                ))
+            }
+            Input::Action(Action::Back) => break,
             _ => continue,
         };
     }
 
+    // The task list only shows top-level tasks, so select the one this task is inside
+    let top_level_task = db.list_path(state_data.task_id).await?.first().copied();
+
     Ok(States::DisplayingTasks(
         DisplayingTasksStates::Normal,
         DisplayingTasksData {
-            selected_task: Some(state_data.task_id),
+            selected_task: top_level_task,
             command_palette_text: "".to_owned(),
             search_string: None,
+            sort_order: config::get().list.sort,
+            running_timer: state_data.running_timer,
         },
     ))
 }
@@ -446,106 +1043,177 @@ pub async fn display_task_fullscreen(
 pub async fn display_tasks(
     db: &mut database::Database,
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
     mut state_data: DisplayingTasksData,
-) -> Result<States, Error> {
-    let mut filtered_tasks = filter_tasks(
-        &db.list_tasks(state_data.search_string.is_some()).await?,
-        &state_data,
-    );
+) -> Result<States> {
+    let mut rows = list_rows(db, &state_data).await?;
+    let tags = db.list_task_tags().await?;
+    let mut pending = vec![];
 
     loop {
-        terminal.draw(|frame| {
+        let tasks: Vec<Task> = rows.iter().map(|row| row.task.clone()).collect();
+
+        let draw = |frame: &mut Frame<CrosstermBackend<Stdout>>| {
             let remaining_space = draw_status_lines(
                 frame,
                 &States::DisplayingTasks(DisplayingTasksStates::Normal, state_data.clone()),
             );
             draw_tasks(
-                &filtered_tasks,
+                &rows,
+                &tags,
                 frame,
                 remaining_space,
                 state_data.selected_task,
-                &state_data,
-                true,
-            )
+            );
+            remaining_space
+        };
+        terminal.draw(|frame| {
+            draw(frame);
         })?;
-        match read()? {
-            Event::Key(event) => match event.code {
-                KeyCode::Char('n') => {
+
+        let key = match read()? {
+            Event::Key(event) => KeyPress::from(event),
+            _ => continue,
+        };
+
+        match keymap.feed(Context::TaskList, &mut pending, key) {
+            Input::Action(Action::ShowHelp) => {
+                show_help(terminal, keymap.bindings(Context::TaskList), draw)?
+            }
+            Input::Action(Action::AddTask) => {
+                return Ok(States::DisplayingTasks(
+                    DisplayingTasksStates::Create,
+                    state_data,
+                ))
+            }
+            Input::Action(Action::AddSubtask) => {
+                if state_data.selected_task.is_some() {
                     return Ok(States::DisplayingTasks(
-                        DisplayingTasksStates::Create,
+                        DisplayingTasksStates::CreateSubtask,
                         state_data,
-                    ))
+                    ));
                 }
-                KeyCode::Char('q') => return Ok(States::Quitting),
-                KeyCode::Char('j') | KeyCode::Down => {
-                    if filtered_tasks.is_empty() {
-                        continue;
-                    }
-                    state_data.selected_task =
-                        match task_index_from_id(&filtered_tasks, state_data.selected_task) {
-                            None => Some(filtered_tasks[0].id),
-                            Some(index) => {
-                                if index + 1 >= filtered_tasks.len() {
-                                    Some(filtered_tasks[0].id)
-                                } else {
-                                    Some(filtered_tasks[index + 1].id)
-                                }
-                            }
-                        }
+            }
+            Input::Action(Action::Quit) => return Ok(States::Quitting),
+            Input::Action(Action::SelectNext) => {
+                state_data.selected_task = select_next(&tasks, state_data.selected_task)
+            }
+            Input::Action(Action::SelectPrevious) => {
+                state_data.selected_task = select_previous(&tasks, state_data.selected_task)
+            }
+            Input::Action(Action::Collapse) => {
+                let index = match task_index_from_id(&tasks, state_data.selected_task) {
+                    Some(index) if rows[index].tree_prefix.is_some() => index,
+                    _ => continue,
+                };
+                let has_shown_subtasks = tasks
+                    .get(index + 1)
+                    .map(|next_task| next_task.parent == Some(tasks[index].id))
+                    .unwrap_or(false);
+
+                if has_shown_subtasks {
+                    db.set_collapsed(tasks[index].id, true).await?;
+                    rows = list_rows(db, &state_data).await?;
+                } else if let Some(parent) = tasks[index].parent {
+                    // There's nothing to collapse, so go up to the parent instead
+                    state_data.selected_task = Some(parent);
                 }
-                KeyCode::Char('k') | KeyCode::Up => {
-                    if filtered_tasks.is_empty() {
-                        continue;
+            }
+            Input::Action(Action::Expand) => {
+                if let Some(index) = task_index_from_id(&tasks, state_data.selected_task) {
+                    if rows[index].collapsed_progress.is_some() {
+                        db.set_collapsed(tasks[index].id, false).await?;
+                        rows = list_rows(db, &state_data).await?;
                     }
-                    state_data.selected_task =
-                        match task_index_from_id(&filtered_tasks, state_data.selected_task) {
-                            None => Some(filtered_tasks[filtered_tasks.len() - 1].id),
-                            Some(0) => Some(filtered_tasks[filtered_tasks.len() - 1].id),
-                            Some(index) => Some(filtered_tasks[index - 1].id),
-                        }
                 }
-                KeyCode::Char('d') => {
-                    let removed_task_index =
-                        task_index_from_id(&filtered_tasks, state_data.selected_task);
-                    match removed_task_index {
-                        None => continue,
-                        Some(index) => {
-                            db.remove_task(filtered_tasks[index].id).await?;
-                            filtered_tasks = filter_tasks(
-                                &db.list_tasks(state_data.search_string.is_some()).await?,
-                                &state_data,
-                            );
-                            state_data.selected_task = if filtered_tasks.is_empty() {
-                                None
-                            } else if index == filtered_tasks.len() {
-                                Some(filtered_tasks[filtered_tasks.len() - 1].id)
+            }
+            Input::Action(Action::Remove) => {
+                let removed_task_index = task_index_from_id(&tasks, state_data.selected_task);
+                match removed_task_index {
+                    None => continue,
+                    Some(index) => {
+                        let removed_tasks = db.remove_task(tasks[index].id).await?;
+                        state_data.command_palette_text = format!(
+                            "Moved {} {} to the trash, press {} to undo",
+                            removed_tasks.len(),
+                            if removed_tasks.len() == 1 {
+                                "task"
                             } else {
-                                Some(filtered_tasks[index].id)
-                            };
-                        }
-                    };
+                                "tasks"
+                            },
+                            keymap.key_for(Context::TaskList, Action::Undo)
+                        );
+                        rows = list_rows(db, &state_data).await?;
+                        state_data.selected_task = if rows.is_empty() {
+                            None
+                        } else if index >= rows.len() {
+                            Some(rows[rows.len() - 1].task.id)
+                        } else {
+                            Some(rows[index].task.id)
+                        };
+                    }
+                };
+            }
+            Input::Action(Action::Edit) => {
+                if state_data.selected_task.is_some() {
+                    return Ok(States::DisplayingTasks(
+                        DisplayingTasksStates::Edit,
+                        state_data,
+                    ));
                 }
-                KeyCode::Char('/') => {
+            }
+            Input::Action(Action::Move) => {
+                if state_data.selected_task.is_some() {
                     return Ok(States::DisplayingTasks(
-                        DisplayingTasksStates::Search,
+                        DisplayingTasksStates::Move,
                         state_data,
                     ));
                 }
-                KeyCode::Enter => {
-                    if let Some(selected_task) = state_data.selected_task {
-                        return Ok(States::DisplayingTaskFullscreen(
-                            DisplayingTaskFullscreenStates::Normal,
-                            DisplayingTaskFullscreenData {
-                                command_palette_text: "Press 'q' to return to the task list or <SPACE> to toggle completion"
-                                    .to_owned(),
-                                task_id: selected_task,
-                                selected_task: None,
-                            },
-                        ));
-                    }
+            }
+            Input::Action(Action::CycleSort) => {
+                state_data.sort_order = state_data.sort_order.next();
+                state_data.command_palette_text =
+                    format!("Sorting by {}", state_data.sort_order.name());
+                rows = list_rows(db, &state_data).await?;
+            }
+            Input::Action(action @ (Action::Undo | Action::Redo)) => {
+                state_data.command_palette_text = undo_or_redo(db, action == Action::Undo).await?;
+                // The tags may have changed too, so start again to load everything
+                return Ok(States::DisplayingTasks(
+                    DisplayingTasksStates::Normal,
+                    state_data,
+                ));
+            }
+            Input::Action(Action::Search) => {
+                return Ok(States::DisplayingTasks(
+                    DisplayingTasksStates::Search,
+                    state_data,
+                ));
+            }
+            Input::Action(Action::SwitchList) => {
+                return Ok(States::DisplayingTasks(
+                    DisplayingTasksStates::SwitchList,
+                    state_data,
+                ));
+            }
+            Input::Action(Action::Open) => {
+                if let Some(selected_task) = state_data.selected_task {
+                    return Ok(States::DisplayingTaskFullscreen(
+                        DisplayingTaskFullscreenStates::Normal,
+                        DisplayingTaskFullscreenData {
+                            command_palette_text: format!(
+                                "Press {} to return to the task list or {} to see every key",
+                                keymap.key_for(Context::TaskFullscreen, Action::Back),
+                                keymap.key_for(Context::TaskFullscreen, Action::ShowHelp)
+                            ),
+                            task_id: selected_task,
+                            selected_task: None,
+                            notes_scroll: 0,
+                            running_timer: state_data.running_timer.clone(),
+                        },
+                    ));
                 }
-                _ => continue,
-            },
+            }
             _ => continue,
         }
     }
@@ -554,28 +1222,35 @@ pub async fn display_tasks(
 pub async fn search_tasks(
     db: &mut database::Database,
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
     mut state_data: DisplayingTasksData,
 ) -> Result<States> {
     let tasks = db.list_tasks(true).await?;
+    let tags = db.list_task_tags().await?;
 
     state_data.command_palette_text = "/".to_owned();
+    let mut pending = vec![];
 
     loop {
         state_data.search_string = Some(
             state_data.command_palette_text[1..state_data.command_palette_text.len()].to_owned(),
         );
+        let rows: Vec<TaskListRow> = filter_tasks(&tasks, &state_data)
+            .into_iter()
+            .map(TaskListRow::from)
+            .collect();
+
         terminal.draw(|frame| {
             let remaining_space = draw_status_lines(
                 frame,
                 &States::DisplayingTasks(DisplayingTasksStates::Search, state_data.clone()),
             );
             draw_tasks(
-                &tasks,
+                &rows,
+                &tags,
                 frame,
                 remaining_space,
                 state_data.selected_task,
-                &state_data,
-                false,
             );
 
             if let Ok(cursor_x) = state_data.command_palette_text.len().try_into() {
@@ -583,23 +1258,25 @@ pub async fn search_tasks(
             }
         })?;
 
-        match read()? {
-            Event::Key(event) => match event.code {
-                KeyCode::Enter => break,
-                KeyCode::Char(char) => state_data.command_palette_text.push(char),
-                KeyCode::Esc => {
-                    state_data.command_palette_text = "".to_owned();
-                    state_data.search_string = None;
-                    break;
-                }
-                KeyCode::Backspace => {
-                    if state_data.command_palette_text.len() > 1 {
-                        state_data.command_palette_text.pop();
-                    }
-                    continue;
+        let key = match read()? {
+            Event::Key(event) => KeyPress::from(event),
+            _ => continue,
+        };
+
+        match keymap.feed(Context::TextInput, &mut pending, key) {
+            Input::Action(Action::Submit) => break,
+            Input::Action(Action::Cancel) => {
+                state_data.command_palette_text = "".to_owned();
+                state_data.search_string = None;
+                break;
+            }
+            Input::Action(Action::DeleteCharacter) => {
+                if state_data.command_palette_text.len() > 1 {
+                    state_data.command_palette_text.pop();
                 }
-                _ => continue,
-            },
+            }
+            Input::Action(Action::ClearText) => state_data.command_palette_text.truncate(1),
+            Input::Unbound(keys) => state_data.command_palette_text += &typed_text(&keys),
             _ => continue,
         }
     }
@@ -617,33 +1294,101 @@ pub async fn search_tasks(
     ))
 }
 
-pub async fn ask_for_tasks(
-    db: &mut database::Database,
+/// Show every key that can be pressed, and what it does, in a box on top of whatever
+/// `draw_background` draws. The box stays open until any key is pressed
+fn show_help(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    mut state_data: DisplayingTasksData,
-) -> Result<States, Error> {
-    let prev_tasks = db.list_tasks(state_data.search_string.is_some()).await?;
+    bindings: &[Binding],
+    mut draw_background: impl FnMut(&mut Frame<CrosstermBackend<Stdout>>) -> Rect,
+) -> Result<()> {
+    let theme = &config::get().theme;
+    let lines: Vec<(String, &str)> = bindings
+        .iter()
+        .filter(|binding| !binding.chords.is_empty())
+        .map(|binding| {
+            (
+                binding
+                    .chords
+                    .iter()
+                    .map(|chord| chord_name(chord))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                binding.description,
+            )
+        })
+        .collect();
+    let keys_width = lines.iter().map(|(keys, _)| keys.len()).max().unwrap_or(0);
+    let descriptions_width = lines
+        .iter()
+        .map(|(_, description)| description.len())
+        .max()
+        .unwrap_or(0);
+
+    terminal.draw(|frame| {
+        let remaining_space = draw_background(frame);
+
+        let width = ((keys_width + descriptions_width + 5) as u16).min(remaining_space.width);
+        let height = (lines.len() as u16 + 2).min(remaining_space.height);
+        let area = Rect {
+            x: remaining_space.x + (remaining_space.width - width) / 2,
+            y: remaining_space.y + (remaining_space.height - height) / 2,
+            width,
+            height,
+        };
 
-    let mut task = String::new();
+        let text: Vec<Spans> = lines
+            .iter()
+            .map(|(keys, description)| {
+                Spans::from(vec![
+                    Span::styled(
+                        format!(" {:width$} ", keys, width = keys_width),
+                        Style::default()
+                            .fg(theme.accent.tui())
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(*description),
+                ])
+            })
+            .collect();
+
+        frame.render_widget(widgets::Clear, area);
+        frame.render_widget(
+            Paragraph::new(text).block(
+                Block::default()
+                    .title("┤ Keys (press any key to close) ├")
+                    .borders(Borders::ALL),
+            ),
+            area,
+        );
+    })?;
+
+    loop {
+        if let Event::Key(_) = read()? {
+            return Ok(());
+        }
+    }
+}
+
+/// Show a text box in the middle of the screen for the user to type into, drawn on top of whatever
+/// `draw_background` draws. The background should return the space left after drawing the status
+/// lines, which the text box will be centred in
+///
+/// Returns the text the user entered once they submit it, or None if they cancelled
+fn ask_for_text(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
+    title: &str,
+    initial_text: &str,
+    mut draw_background: impl FnMut(&mut Frame<CrosstermBackend<Stdout>>) -> Rect,
+) -> Result<Option<String>> {
+    let mut text = initial_text.to_owned();
+    let mut pending = vec![];
 
-    state_data.command_palette_text =
-        "Press <ENTER> to finish adding the task or <ESCAPE> to cancel".to_owned();
     loop {
         terminal.draw(|frame| {
-            let remaining_space = draw_status_lines(
-                frame,
-                &States::DisplayingTasks(DisplayingTasksStates::Create, state_data.clone()),
-            );
-            draw_tasks(
-                &prev_tasks,
-                frame,
-                remaining_space,
-                None,
-                &state_data,
-                false,
-            );
+            let remaining_space = draw_background(frame);
 
-            let block = Block::default().title("┤ New task ├").borders(Borders::ALL);
+            let block = Block::default().title(title).borders(Borders::ALL);
 
             let width = 42;
             let height = 3;
@@ -659,15 +1404,15 @@ pub async fn ask_for_tasks(
             };
 
             let inner_area = block.inner(size);
-            let task_length = task.len();
-            let displayed_text = if task_length + 1 > inner_area.width.into() {
+            let text_length = text.len();
+            let displayed_text = if text_length + 1 > inner_area.width.into() {
                 "...".to_owned()
-                    + (task
+                    + (text
                         .clone()
-                        .split_at(task_length - <u16 as Into<usize>>::into(inner_area.width - 4))
+                        .split_at(text_length - <u16 as Into<usize>>::into(inner_area.width - 4))
                         .1)
             } else {
-                task.clone()
+                text.clone()
             };
             if let Ok(text_length) = <usize as TryInto<u16>>::try_into(displayed_text.len()) {
                 frame.set_cursor(x + text_length + 1, y + 1);
@@ -678,31 +1423,402 @@ pub async fn ask_for_tasks(
             frame.render_widget(text_widget, inner_area);
         })?;
 
-        match read()? {
-            Event::Key(event) => match event.code {
-                KeyCode::Enter => {
-                    state_data.command_palette_text = "".to_owned();
-                    break;
-                }
-                KeyCode::Char(char) => task.push(char),
-                KeyCode::Esc => {
-                    state_data.command_palette_text = "".to_owned();
-                    return Ok(States::DisplayingTasks(
-                        DisplayingTasksStates::Normal,
-                        state_data,
-                    ));
-                }
-                KeyCode::Backspace => {
-                    task.pop();
+        let key = match read()? {
+            Event::Key(event) => KeyPress::from(event),
+            _ => continue,
+        };
+
+        match keymap.feed(Context::TextInput, &mut pending, key) {
+            Input::Action(Action::Submit) => return Ok(Some(text)),
+            Input::Action(Action::Cancel) => return Ok(None),
+            Input::Action(Action::DeleteCharacter) => {
+                text.pop();
+            }
+            Input::Action(Action::ClearText) => text.clear(),
+            Input::Unbound(keys) => text += &typed_text(&keys),
+            _ => continue,
+        }
+    }
+}
+
+/// The text typed by keys which don't do anything in a text input. Keys that aren't characters, or
+/// are pressed along with control or alt, don't type anything
+fn typed_text(keys: &[KeyPress]) -> String {
+    keys.iter()
+        .filter_map(|key| match key.code {
+            KeyCode::Char(character)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                Some(character)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// A message for the command palette explaining how to finish or cancel typing into a text input
+fn text_input_help(keymap: &Keymap, finish: &str) -> String {
+    format!(
+        "Press {} to {} or {} to cancel",
+        keymap.key_for(Context::TextInput, Action::Submit),
+        finish,
+        keymap.key_for(Context::TextInput, Action::Cancel)
+    )
+}
+
+/// Ask for a new task and add it, either at the top level or, if a parent is given, as a subtask of
+/// that parent
+pub async fn ask_for_tasks(
+    db: &mut database::Database,
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
+    mut state_data: DisplayingTasksData,
+    parent: Option<i64>,
+) -> Result<States> {
+    let rows = list_rows(db, &state_data).await?;
+    let tags = db.list_task_tags().await?;
+
+    let (state, title) = match parent {
+        Some(_) => (DisplayingTasksStates::CreateSubtask, "┤ New subtask ├"),
+        None => (DisplayingTasksStates::Create, "┤ New task ├"),
+    };
+
+    state_data.command_palette_text = text_input_help(keymap, "finish adding the task");
+    let task = ask_for_text(terminal, keymap, title, "", |frame| {
+        let remaining_space =
+            draw_status_lines(frame, &States::DisplayingTasks(state, state_data.clone()));
+        draw_tasks(&rows, &tags, frame, remaining_space, parent);
+        remaining_space
+    })?;
+    state_data.command_palette_text = "".to_owned();
+
+    if let Some(task) = task {
+        let (description, tags) = split_tags(&task);
+        if !description.is_empty() {
+            let new_task = db
+                .add_task(&NewTask {
+                    tags,
+                    ..NewTask::new(&description, parent)
+                })
+                .await?;
+
+            if let Some(parent) = parent {
+                // Make sure the new subtask can be seen
+                db.set_collapsed(parent, false).await?;
+            }
+            state_data.selected_task = Some(new_task.id);
+        }
+    }
+
+    Ok(States::DisplayingTasks(
+        DisplayingTasksStates::Normal,
+        state_data,
+    ))
+}
+
+/// Change a task's description to whatever the user typed, adding any tags they typed along with it.
+/// Returns a message for the command palette describing what happened
+async fn update_description(
+    db: &mut database::Database,
+    task_id: i64,
+    text: Option<String>,
+) -> Result<String> {
+    let text = match text {
+        Some(text) => text,
+        None => return Ok("".to_owned()),
+    };
+
+    let (description, tags) = split_tags(&text);
+    if description.is_empty() {
+        return Ok("A task's description can't be empty, so nothing was changed".to_owned());
+    }
+
+    // The new description and tags are undone together
+    db.begin_change(&format!("edit task {}", task_id)).await?;
+    let result = async {
+        db.update_task(
+            task_id,
+            &TaskUpdate {
+                description: Some(description),
+                ..Default::default()
+            },
+        )
+        .await?;
+        for tag in tags {
+            db.tag_task(task_id, &tag).await?;
+        }
+        Ok::<_, sqlx::Error>(())
+    }
+    .await;
+    db.end_change().await?;
+    result?;
+
+    Ok("".to_owned())
+}
+
+/// Undoes or redoes the last change, returning a message for the command palette saying what
+/// happened
+async fn undo_or_redo(db: &mut database::Database, undoing: bool) -> Result<String> {
+    Ok(match (undoing, db.undo_or_redo(undoing).await?) {
+        (true, Some(description)) => format!("Undid '{}'", description),
+        (false, Some(description)) => format!("Redid '{}'", description),
+        (true, None) => "There's nothing to undo".to_owned(),
+        (false, None) => "There's nothing to redo".to_owned(),
+    })
+}
+
+pub async fn edit_task(
+    db: &mut database::Database,
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
+    mut state_data: DisplayingTasksData,
+) -> Result<States> {
+    let rows = list_rows(db, &state_data).await?;
+    let tags = db.list_task_tags().await?;
+
+    if let Some(task_id) = state_data.selected_task {
+        let task = db.get_task(task_id).await?;
+
+        state_data.command_palette_text = text_input_help(keymap, "finish editing the task");
+        let text = ask_for_text(
+            terminal,
+            keymap,
+            "┤ Edit task ├",
+            &task.description,
+            |frame| {
+                let remaining_space = draw_status_lines(
+                    frame,
+                    &States::DisplayingTasks(DisplayingTasksStates::Edit, state_data.clone()),
+                );
+                draw_tasks(
+                    &rows,
+                    &tags,
+                    frame,
+                    remaining_space,
+                    state_data.selected_task,
+                );
+                remaining_space
+            },
+        )?;
+        state_data.command_palette_text = update_description(db, task_id, text).await?;
+    }
+
+    Ok(States::DisplayingTasks(
+        DisplayingTasksStates::Normal,
+        state_data,
+    ))
+}
+
+/// Let the user pick a new parent for the selected task from every task that isn't already inside
+/// it, or move it to the top level
+pub async fn move_task(
+    db: &mut database::Database,
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
+    mut state_data: DisplayingTasksData,
+) -> Result<States> {
+    let task_id = match state_data.selected_task {
+        Some(task_id) => task_id,
+        None => {
+            return Ok(States::DisplayingTasks(
+                DisplayingTasksStates::Normal,
+                state_data,
+            ))
+        }
+    };
+
+    // A task can't be moved under itself or anything inside it, so don't offer those as parents
+    let mut subtree_ids = vec![];
+    let mut unvisited = vec![db.list_subtasks(task_id).await?];
+    while let Some(task_tree) = unvisited.pop() {
+        subtree_ids.push(task_tree.id);
+        unvisited.extend(task_tree.children);
+    }
+    let candidates = sort(
+        db.list_tasks(true)
+            .await?
+            .into_iter()
+            .filter(|task| !subtree_ids.contains(&task.id))
+            .collect(),
+        state_data.sort_order,
+    );
+    let candidate_rows: Vec<TaskListRow> =
+        candidates.iter().cloned().map(TaskListRow::from).collect();
+    let tags = db.list_task_tags().await?;
+
+    state_data.command_palette_text = format!(
+        "Pick a new parent for task {} and press {}, press {} to move it to the top level or {} to cancel",
+        task_id,
+        keymap.key_for(Context::MovePicker, Action::Move),
+        keymap.key_for(Context::MovePicker, Action::MoveToTop),
+        keymap.key_for(Context::MovePicker, Action::Cancel)
+    );
+    let mut new_parent = None;
+    let mut pending = vec![];
+
+    let chosen_parent = loop {
+        let draw = |frame: &mut Frame<CrosstermBackend<Stdout>>| {
+            let remaining_space = draw_status_lines(
+                frame,
+                &States::DisplayingTasks(DisplayingTasksStates::Move, state_data.clone()),
+            );
+            draw_tasks(&candidate_rows, &tags, frame, remaining_space, new_parent);
+            remaining_space
+        };
+        terminal.draw(|frame| {
+            draw(frame);
+        })?;
+
+        let key = match read()? {
+            Event::Key(event) => KeyPress::from(event),
+            _ => continue,
+        };
+
+        match keymap.feed(Context::MovePicker, &mut pending, key) {
+            Input::Action(Action::ShowHelp) => {
+                show_help(terminal, keymap.bindings(Context::MovePicker), draw)?
+            }
+            Input::Action(Action::SelectNext) => new_parent = select_next(&candidates, new_parent),
+            Input::Action(Action::SelectPrevious) => {
+                new_parent = select_previous(&candidates, new_parent)
+            }
+            Input::Action(Action::Move) => {
+                if new_parent.is_some() {
+                    break Some(new_parent);
                 }
-                _ => continue,
+            }
+            Input::Action(Action::MoveToTop) => break Some(None),
+            Input::Action(Action::Cancel) => break None,
+            _ => continue,
+        }
+    };
+
+    state_data.command_palette_text = match chosen_parent {
+        Some(new_parent) => match db.move_task(task_id, new_parent).await {
+            Ok(_) => match new_parent {
+                Some(new_parent) => format!("Moved task {} under task {}", task_id, new_parent),
+                None => format!("Moved task {} to the top level", task_id),
             },
+            Err(error @ MoveTaskError::Cycle) => error.to_string(),
+            Err(MoveTaskError::Database(error)) => return Err(error.into()),
+        },
+        None => "".to_owned(),
+    };
+
+    Ok(States::DisplayingTasks(
+        DisplayingTasksStates::Normal,
+        state_data,
+    ))
+}
+
+/// Let the user pick another task list in a box on top of the task list, and switch to it
+pub async fn switch_list(
+    db: &mut database::Database,
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
+    lists: &mut TaskLists,
+    mut state_data: DisplayingTasksData,
+) -> Result<States> {
+    let rows = list_rows(db, &state_data).await?;
+    let tags = db.list_task_tags().await?;
+    let names = lists.names()?;
+    let mut selected = names
+        .iter()
+        .position(|name| lists.current.as_ref() == Some(name))
+        .unwrap_or(0);
+    let mut pending = vec![];
+
+    state_data.command_palette_text = format!(
+        "Pick a list and press {} to switch to it, or {} to stay on this one",
+        keymap.key_for(Context::ListPicker, Action::Open),
+        keymap.key_for(Context::ListPicker, Action::Cancel)
+    );
+
+    let chosen_list = loop {
+        terminal.draw(|frame| {
+            let theme = &config::get().theme;
+            let remaining_space = draw_status_lines(
+                frame,
+                &States::DisplayingTasks(DisplayingTasksStates::SwitchList, state_data.clone()),
+            );
+            draw_tasks(
+                &rows,
+                &tags,
+                frame,
+                remaining_space,
+                state_data.selected_task,
+            );
+
+            let lines: Vec<String> = names
+                .iter()
+                .map(|name| match lists.current.as_ref() == Some(name) {
+                    true => format!(" {} (current)", name),
+                    false => format!(" {}", name),
+                })
+                .collect();
+            let width = (lines.iter().map(String::len).max().unwrap_or(0) as u16 + 4)
+                .max(12)
+                .min(remaining_space.width);
+            let height = (lines.len() as u16 + 2).min(remaining_space.height);
+            let area = Rect {
+                x: remaining_space.x + (remaining_space.width - width) / 2,
+                y: remaining_space.y + (remaining_space.height - height) / 2,
+                width,
+                height,
+            };
+
+            let list = widgets::List::new(
+                lines
+                    .into_iter()
+                    .map(widgets::ListItem::new)
+                    .collect::<Vec<_>>(),
+            )
+            .highlight_style(
+                Style::default()
+                    .bg(theme.accent.tui())
+                    .fg(theme.accent_text.tui())
+                    .add_modifier(Modifier::BOLD),
+            )
+            .block(Block::default().title("┤ Lists ├").borders(Borders::ALL));
+            let mut list_state = ListState::default();
+            list_state.select(Some(selected));
+
+            frame.render_widget(widgets::Clear, area);
+            frame.render_stateful_widget(list, area, &mut list_state);
+        })?;
+
+        let key = match read()? {
+            Event::Key(event) => KeyPress::from(event),
+            _ => continue,
+        };
+
+        match keymap.feed(Context::ListPicker, &mut pending, key) {
+            Input::Action(Action::SelectNext) => selected = (selected + 1).min(names.len() - 1),
+            Input::Action(Action::SelectPrevious) => selected = selected.saturating_sub(1),
+            Input::Action(Action::Open) => break Some(&names[selected]),
+            Input::Action(Action::Cancel) => break None,
             _ => continue,
         }
-    }
+    };
 
-    let new_task = db.add_task(&task, None).await?;
-    state_data.selected_task = Some(new_task.id);
+    state_data.command_palette_text = "".to_owned();
+    if let Some(name) = chosen_list.filter(|name| lists.current.as_ref() != Some(name)) {
+        let path = lists.path(name)?;
+        let mut new_db = Database::new(Some(path.to_string_lossy().into_owned())).await?;
+        new_db.setup().await?;
+        std::mem::replace(db, new_db).close().await?;
+        lists.switch_to(name)?;
+
+        state_data = DisplayingTasksData {
+            selected_task: None,
+            command_palette_text: format!("Switched to the {} list", name),
+            search_string: None,
+            sort_order: state_data.sort_order,
+            running_timer: state_data.running_timer,
+        };
+    }
 
     Ok(States::DisplayingTasks(
         DisplayingTasksStates::Normal,
@@ -710,29 +1826,142 @@ pub async fn ask_for_tasks(
     ))
 }
 
+/// Ask for a new task and add it as a subtask of the task being viewed, or of its selected subtask
+pub async fn add_subtask_of(
+    db: &mut database::Database,
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
+    mut state_data: DisplayingTaskFullscreenData,
+) -> Result<States> {
+    let task_tree = db.list_subtasks(state_data.task_id).await?;
+    let progress = db.list_progress().await?;
+    let time_spent = db.list_time_spent(Local::now().timestamp()).await?;
+    let parent = state_data.selected_task.unwrap_or(state_data.task_id);
+
+    let previous_command_palette_text = state_data.command_palette_text.clone();
+    state_data.command_palette_text = text_input_help(keymap, "finish adding the subtask");
+    let text = ask_for_text(terminal, keymap, "┤ New subtask ├", "", |frame| {
+        draw_task_fullscreen(
+            frame,
+            &task_tree,
+            &progress,
+            &time_spent,
+            DisplayingTaskFullscreenStates::Create,
+            &state_data,
+        )
+    })?;
+    state_data.command_palette_text = previous_command_palette_text;
+
+    if let Some(text) = text {
+        let (description, tags) = split_tags(&text);
+        if description.is_empty() {
+            state_data.command_palette_text =
+                "A task's description can't be empty, so nothing was added".to_owned();
+        } else {
+            db.add_task(&NewTask {
+                tags,
+                ..NewTask::new(&description, Some(parent))
+            })
+            .await?;
+        }
+    }
+
+    Ok(States::DisplayingTaskFullscreen(
+        DisplayingTaskFullscreenStates::Normal,
+        state_data,
+    ))
+}
+
+pub async fn edit_task_fullscreen(
+    db: &mut database::Database,
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    keymap: &Keymap,
+    mut state_data: DisplayingTaskFullscreenData,
+) -> Result<States> {
+    let task_tree = db.list_subtasks(state_data.task_id).await?;
+    let progress = db.list_progress().await?;
+    let time_spent = db.list_time_spent(Local::now().timestamp()).await?;
+    let task_id = state_data.selected_task.unwrap_or(state_data.task_id);
+    let task = db.get_task(task_id).await?;
+
+    let previous_command_palette_text = state_data.command_palette_text.clone();
+    state_data.command_palette_text = text_input_help(keymap, "finish editing the task");
+    let text = ask_for_text(
+        terminal,
+        keymap,
+        "┤ Edit task ├",
+        &task.description,
+        |frame| {
+            draw_task_fullscreen(
+                frame,
+                &task_tree,
+                &progress,
+                &time_spent,
+                DisplayingTaskFullscreenStates::Edit,
+                &state_data,
+            )
+        },
+    )?;
+
+    let message = update_description(db, task_id, text).await?;
+    state_data.command_palette_text = if message.is_empty() {
+        previous_command_palette_text
+    } else {
+        message
+    };
+
+    Ok(States::DisplayingTaskFullscreen(
+        DisplayingTaskFullscreenStates::Normal,
+        state_data,
+    ))
+}
+
 pub async fn display_state(
-    state: States,
+    mut state: States,
     terminal: &mut tui::Terminal<CrosstermBackend<Stdout>>,
     db: &mut Database,
+    keymap: &Keymap,
+    lists: &mut TaskLists,
 ) -> Result<States> {
+    let running_timer = db.running_timer().await?;
+    match &mut state {
+        States::DisplayingTasks(_, state_data) => state_data.running_timer = running_timer,
+        States::DisplayingTaskFullscreen(_, state_data) => state_data.running_timer = running_timer,
+        States::Quitting => {}
+    }
+
     match state {
         States::DisplayingTasks(DisplayingTasksStates::Normal, state_data) => {
-            Ok(display_tasks(db, terminal, state_data).await?)
+            Ok(display_tasks(db, terminal, keymap, state_data).await?)
         }
         States::DisplayingTasks(DisplayingTasksStates::Create, state_data) => {
-            Ok(ask_for_tasks(db, terminal, state_data).await?)
+            Ok(ask_for_tasks(db, terminal, keymap, state_data, None).await?)
+        }
+        States::DisplayingTasks(DisplayingTasksStates::CreateSubtask, state_data) => {
+            let parent = state_data.selected_task;
+            Ok(ask_for_tasks(db, terminal, keymap, state_data, parent).await?)
+        }
+        States::DisplayingTasks(DisplayingTasksStates::Edit, state_data) => {
+            Ok(edit_task(db, terminal, keymap, state_data).await?)
         }
         States::DisplayingTasks(DisplayingTasksStates::Search, state_data) => {
-            Ok(search_tasks(db, terminal, state_data).await?)
+            Ok(search_tasks(db, terminal, keymap, state_data).await?)
+        }
+        States::DisplayingTasks(DisplayingTasksStates::Move, state_data) => {
+            Ok(move_task(db, terminal, keymap, state_data).await?)
+        }
+        States::DisplayingTasks(DisplayingTasksStates::SwitchList, state_data) => {
+            Ok(switch_list(db, terminal, keymap, lists, state_data).await?)
         }
         States::DisplayingTaskFullscreen(DisplayingTaskFullscreenStates::Normal, state_data) => {
-            Ok(display_task_fullscreen(db, terminal, state_data).await?)
+            Ok(display_task_fullscreen(db, terminal, keymap, state_data).await?)
+        }
+        States::DisplayingTaskFullscreen(DisplayingTaskFullscreenStates::Edit, state_data) => {
+            Ok(edit_task_fullscreen(db, terminal, keymap, state_data).await?)
+        }
+        States::DisplayingTaskFullscreen(DisplayingTaskFullscreenStates::Create, state_data) => {
+            Ok(add_subtask_of(db, terminal, keymap, state_data).await?)
         }
-        // States::DisplayingTaskFullscreen(DisplayingTaskFullscreenStates::Create, _state_data) => {
-        //     todo!()
-        //     // Ok(add_subtask_of(db, terminal, state_data).await?)
-        // }
-
         States::Quitting => panic!("display_state called when the application is already quitting"),
     }
 }